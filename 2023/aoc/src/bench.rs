@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use common::bench::Measurement;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Baseline {
+    pub name: String,
+    pub measurements: Vec<Measurement>,
+}
+
+#[derive(Debug)]
+pub struct BenchOptions {
+    /// Directory holding the `dayN` crates.
+    pub root: PathBuf,
+    /// Days to run, or every day found under `root`.
+    pub days: Option<Vec<u32>>,
+    pub save: Option<String>,
+    pub baseline: Option<String>,
+    /// Slowdown of the median, in percent, reported as a regression.
+    pub threshold: f64,
+}
+
+impl BenchOptions {
+    pub fn parse(args: &[String]) -> Result<BenchOptions, String> {
+        let mut options = BenchOptions {
+            root: Path::new(env!("CARGO_MANIFEST_DIR"))
+                .parent()
+                .unwrap()
+                .to_path_buf(),
+            days: None,
+            save: None,
+            baseline: None,
+            threshold: 10.0,
+        };
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+            match arg.as_str() {
+                "--root" => options.root = PathBuf::from(value()?),
+                "--days" => {
+                    let days = value()?
+                        .split(',')
+                        .map(|day| day.trim().parse().map_err(|_| format!("Invalid day {day}")))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    options.days = Some(days);
+                }
+                "--save" => options.save = Some(value()?.clone()),
+                "--baseline" => options.baseline = Some(value()?.clone()),
+                "--threshold" => {
+                    let threshold = value()?;
+                    options.threshold = threshold
+                        .parse()
+                        .map_err(|_| format!("Invalid threshold {threshold}"))?;
+                }
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn baseline_path(config: &Config, name: &str) -> PathBuf {
+    config.cache_dir.join("bench").join(format!("{name}.json"))
+}
+
+/// Every `dayN` crate under `root`, in day order.
+fn find_days(root: &Path) -> Result<Vec<u32>, String> {
+    let entries = std::fs::read_dir(root).map_err(|err| format!("{}: {err}", root.display()))?;
+    let mut days = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("Cargo.toml").exists())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("day")?
+                .parse()
+                .ok()
+        })
+        .collect::<Vec<u32>>();
+    days.sort_unstable();
+    Ok(days)
+}
+
+fn bench_day(root: &Path, day: u32) -> Result<Vec<Measurement>, String> {
+    let dir = root.join(format!("day{day}"));
+    eprintln!("Benchmarking day {day}...");
+    let output = Command::new("cargo")
+        .args(["run", "--release", "--quiet", "--", "bench"])
+        .current_dir(&dir)
+        .output()
+        .map_err(|err| format!("{}: {err}", dir.display()))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Day {day} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .map(|line| serde_json::from_str(line).map_err(|err| format!("Day {day}: {err}")))
+        .collect()
+}
+
+/// Runs the benchmarks, optionally saving them and comparing them with an earlier baseline.
+/// Returns the number of regressions.
+pub fn run(config: &Config, options: &BenchOptions) -> Result<usize, String> {
+    let baseline = match &options.baseline {
+        Some(name) => {
+            let path = baseline_path(config, name);
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+            Some(
+                serde_json::from_str::<Baseline>(&contents)
+                    .map_err(|err| format!("{}: {err}", path.display()))?,
+            )
+        }
+        None => None,
+    };
+
+    let days = match &options.days {
+        Some(days) => days.clone(),
+        None => find_days(&options.root)?,
+    };
+    let mut measurements = Vec::new();
+    for day in days {
+        measurements.extend(bench_day(&options.root, day)?);
+    }
+
+    let mut regressions = 0;
+    println!(
+        "{:<8} {:<16} {:>14} {:>14} {:>9}",
+        "day", "phase", "median", "baseline", "change"
+    );
+    for measurement in &measurements {
+        let previous = baseline.as_ref().and_then(|baseline| {
+            baseline
+                .measurements
+                .iter()
+                .find(|m| m.day == measurement.day && m.phase == measurement.phase)
+        });
+        // Change of the median against the baseline, in percent.
+        let change =
+            previous.map(|previous| (measurement.median_ns / previous.median_ns - 1.0) * 100.0);
+        let flag = match change {
+            Some(change) if change > options.threshold => {
+                regressions += 1;
+                "  REGRESSION"
+            }
+            Some(change) if change < -options.threshold => "  improved",
+            _ => "",
+        };
+        let previous = previous.map_or("-".to_string(), |previous| format_ns(previous.median_ns));
+        let change = change.map_or("-".to_string(), |change| format!("{change:+.1}%"));
+        println!(
+            "{:<8} {:<16} {:>14} {:>14} {:>9}{}",
+            measurement.day,
+            measurement.phase,
+            format_ns(measurement.median_ns),
+            previous,
+            change,
+            flag
+        );
+    }
+
+    if let Some(name) = &options.save {
+        let path = baseline_path(config, name);
+        let baseline = Baseline {
+            name: name.clone(),
+            measurements,
+        };
+        let dir = path.parent().unwrap();
+        std::fs::create_dir_all(dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+        let json = serde_json::to_string_pretty(&baseline).map_err(|err| err.to_string())?;
+        std::fs::write(&path, json).map_err(|err| format!("{}: {err}", path.display()))?;
+        eprintln!("Saved baseline to {}", path.display());
+    }
+
+    Ok(regressions)
+}
+
+fn format_ns(ns: f64) -> String {
+    match ns {
+        ns if ns >= 1e9 => format!("{:.2} s", ns / 1e9),
+        ns if ns >= 1e6 => format!("{:.2} ms", ns / 1e6),
+        ns if ns >= 1e3 => format!("{:.2} µs", ns / 1e3),
+        ns => format!("{ns:.0} ns"),
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_BASE_URL: &str = "https://adventofcode.com";
+const DEFAULT_CACHE_DIR: &str = ".aoc-cache";
+const DEFAULT_THROTTLE_SECS: u64 = 5;
+
+/// Settings for talking to the Advent of Code site.
+///
+/// Read from `key = value` lines in `$AOC_CONFIG` (default `~/.config/aoc/config`), then
+/// overridden by the `AOC_SESSION`, `AOC_BASE_URL`, `AOC_CACHE_DIR` and `AOC_THROTTLE_SECS`
+/// environment variables.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub session: Option<String>,
+    pub base_url: String,
+    pub cache_dir: PathBuf,
+    /// Minimum time between two requests to the site.
+    pub throttle: Duration,
+}
+
+impl Config {
+    pub fn load() -> Result<Config, String> {
+        let path = std::env::var_os("AOC_CONFIG")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/aoc/config"))
+            });
+
+        let mut values = match path {
+            Some(path) if path.exists() => {
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("{}: {err}", path.display()))?;
+                parse_config(&contents).map_err(|err| format!("{}: {err}", path.display()))?
+            }
+            _ => HashMap::new(),
+        };
+
+        for (key, var) in [
+            ("session", "AOC_SESSION"),
+            ("base_url", "AOC_BASE_URL"),
+            ("cache_dir", "AOC_CACHE_DIR"),
+            ("throttle_secs", "AOC_THROTTLE_SECS"),
+        ] {
+            if let Ok(value) = std::env::var(var) {
+                values.insert(key.to_string(), value);
+            }
+        }
+
+        let throttle = match values.get("throttle_secs") {
+            Some(secs) => secs
+                .parse()
+                .map_err(|_| format!("Invalid throttle_secs {secs:?}"))?,
+            None => DEFAULT_THROTTLE_SECS,
+        };
+
+        Ok(Config {
+            session: values
+                .remove("session")
+                .filter(|session| !session.is_empty()),
+            base_url: values
+                .remove("base_url")
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            cache_dir: PathBuf::from(
+                values
+                    .remove("cache_dir")
+                    .unwrap_or_else(|| DEFAULT_CACHE_DIR.to_string()),
+            ),
+            throttle: Duration::from_secs(throttle),
+        })
+    }
+
+    pub fn session(&self) -> Result<&str, String> {
+        self.session
+            .as_deref()
+            .ok_or("No session token, set AOC_SESSION or `session` in the config file".to_string())
+    }
+}
+
+fn parse_config(contents: &str) -> Result<HashMap<String, String>, String> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(n, line)| (n, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(format!("line {}: expected `key = value`", n + 1))?;
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_key_values() {
+        let values = parse_config("session = abc\nbase_url=http://localhost:8000/\n").unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values["session"], "abc");
+        assert_eq!(values["base_url"], "http://localhost:8000/");
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let values =
+            parse_config("# my token\n\n   \n  # indented comment\nsession = abc\n").unwrap();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values["session"], "abc");
+    }
+
+    #[test]
+    fn value_may_contain_equals() {
+        let values = parse_config("session = a=b\n").unwrap();
+        assert_eq!(values["session"], "a=b");
+    }
+
+    #[test]
+    fn malformed_line_reports_its_number() {
+        let err = parse_config("# comment\nsession = abc\ncache_dir\n").unwrap_err();
+        assert_eq!(err, "line 3: expected `key = value`");
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::http;
+
+pub fn input_path(config: &Config, year: u32, day: u32) -> PathBuf {
+    config
+        .cache_dir
+        .join(year.to_string())
+        .join(format!("day{day}"))
+        .join("input")
+}
+
+/// Downloads the puzzle input into the cache, unless it is already there.
+/// Returns the path of the cached file and whether it was downloaded now.
+pub fn fetch(config: &Config, year: u32, day: u32) -> Result<(PathBuf, bool), String> {
+    let path = input_path(config, year, day);
+    if path.exists() {
+        return Ok((path, false));
+    }
+
+    let response = http::get(config, &format!("/{year}/day/{day}/input"))?;
+    match response.status {
+        200 => {}
+        404 => return Err(format!("Day {day} of {year} is not available yet")),
+        400 | 500 => return Err("The server rejected the session token".to_string()),
+        status => {
+            return Err(format!(
+                "Unexpected status {status}: {}",
+                response.body.trim()
+            ))
+        }
+    }
+
+    let dir = path.parent().unwrap();
+    std::fs::create_dir_all(dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+    std::fs::write(&path, response.body).map_err(|err| format!("{}: {err}", path.display()))?;
+    Ok((path, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stand_in::StandIn;
+
+    #[test]
+    fn downloads_into_cache() {
+        let cache = tempfile::tempdir().unwrap();
+        let server = StandIn::serve(vec![(200, "1 2 3\n")]);
+        let config = server.config(&cache);
+
+        let (path, downloaded) = fetch(&config, 2023, 5).unwrap();
+        assert!(downloaded);
+        assert_eq!(path, cache.path().join("2023/day5/input"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1 2 3\n");
+
+        let requests = server.finish();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].path, "/2023/day/5/input");
+        assert!(requests[0]
+            .headers
+            .iter()
+            .any(|header| header == "Cookie: session=secret"));
+    }
+
+    #[test]
+    fn cached_input_is_not_downloaded_again() {
+        let cache = tempfile::tempdir().unwrap();
+        let server = StandIn::serve(vec![(200, "input")]);
+        let config = server.config(&cache);
+
+        fetch(&config, 2023, 1).unwrap();
+        let (path, downloaded) = fetch(&config, 2023, 1).unwrap();
+        assert!(!downloaded);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "input");
+        assert_eq!(server.finish().len(), 1);
+    }
+
+    #[test]
+    fn maps_error_statuses() {
+        let cache = tempfile::tempdir().unwrap();
+        let server = StandIn::serve(vec![
+            (404, "Not found"),
+            (400, "Bad"),
+            (500, "Oops"),
+            (503, " Busy \n"),
+        ]);
+        let config = server.config(&cache);
+
+        assert_eq!(
+            fetch(&config, 2023, 25).unwrap_err(),
+            "Day 25 of 2023 is not available yet"
+        );
+        assert_eq!(
+            fetch(&config, 2023, 1).unwrap_err(),
+            "The server rejected the session token"
+        );
+        assert_eq!(
+            fetch(&config, 2023, 2).unwrap_err(),
+            "The server rejected the session token"
+        );
+        assert_eq!(
+            fetch(&config, 2023, 3).unwrap_err(),
+            "Unexpected status 503: Busy"
+        );
+        assert!(!input_path(&config, 2023, 1).exists());
+        server.finish();
+    }
+
+    #[test]
+    fn missing_session_is_an_error() {
+        let cache = tempfile::tempdir().unwrap();
+        let server = StandIn::serve(Vec::new());
+        let config = Config {
+            session: None,
+            ..server.config(&cache)
+        };
+
+        let err = fetch(&config, 2023, 1).unwrap_err();
+        assert!(err.starts_with("No session token"), "{err}");
+        assert!(server.finish().is_empty());
+    }
+}
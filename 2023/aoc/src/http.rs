@@ -0,0 +1,131 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+
+const USER_AGENT: &str = "github.com/ariponce/adventOfCode aoc-cli";
+const LAST_REQUEST_FILE: &str = ".last_request";
+
+/// Response of a request that reached the server.
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+pub fn get(config: &Config, path: &str) -> Result<Response, String> {
+    let session = config.session()?;
+    throttle(config)?;
+
+    let url = format!("{}{}", config.base_url, path);
+    let result = ureq::get(&url)
+        .set("Cookie", &format!("session={session}"))
+        .set("User-Agent", USER_AGENT)
+        .call();
+    into_response(&url, result)
+}
+
+pub fn post(config: &Config, path: &str, form: &[(&str, &str)]) -> Result<Response, String> {
+    let session = config.session()?;
+    throttle(config)?;
+
+    let url = format!("{}{}", config.base_url, path);
+    let result = ureq::post(&url)
+        .set("Cookie", &format!("session={session}"))
+        .set("User-Agent", USER_AGENT)
+        .send_form(form);
+    into_response(&url, result)
+}
+
+fn into_response(
+    url: &str,
+    result: Result<ureq::Response, ureq::Error>,
+) -> Result<Response, String> {
+    let response = match result {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(err) => return Err(format!("{url}: {err}")),
+    };
+    let status = response.status();
+    let body = response
+        .into_string()
+        .map_err(|err| format!("{url}: {err}"))?;
+    Ok(Response { status, body })
+}
+
+/// Sleeps until `config.throttle` has passed since the last request, which is remembered in
+/// the cache directory so that separate runs share it.
+fn throttle(config: &Config) -> Result<(), String> {
+    let file = config.cache_dir.join(LAST_REQUEST_FILE);
+    if let Some(last) = read_timestamp(&file) {
+        let elapsed = now().saturating_sub(last);
+        if elapsed < config.throttle {
+            let wait = config.throttle - elapsed;
+            eprintln!("Waiting {:.1}s before the next request", wait.as_secs_f64());
+            std::thread::sleep(wait);
+        }
+    }
+
+    std::fs::create_dir_all(&config.cache_dir)
+        .map_err(|err| format!("{}: {err}", config.cache_dir.display()))?;
+    std::fs::write(&file, now().as_millis().to_string())
+        .map_err(|err| format!("{}: {err}", file.display()))
+}
+
+fn read_timestamp(file: &Path) -> Option<Duration> {
+    let millis = std::fs::read_to_string(file).ok()?.trim().parse().ok()?;
+    Some(Duration::from_millis(millis))
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn config(cache_dir: &Path, throttle: Duration) -> Config {
+        Config {
+            session: Some("secret".to_string()),
+            base_url: String::new(),
+            cache_dir: cache_dir.to_path_buf(),
+            throttle,
+        }
+    }
+
+    #[test]
+    fn throttle_waits_for_recent_request() {
+        let cache = tempfile::tempdir().unwrap();
+        let config = config(cache.path(), Duration::from_millis(300));
+        let file = cache.path().join(LAST_REQUEST_FILE);
+        std::fs::write(&file, now().as_millis().to_string()).unwrap();
+
+        let start = Instant::now();
+        throttle(&config).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(250));
+        assert!(read_timestamp(&file).unwrap() >= now() - Duration::from_secs(1));
+    }
+
+    #[test]
+    fn throttle_skips_old_or_missing_request() {
+        let cache = tempfile::tempdir().unwrap();
+        let config = config(cache.path(), Duration::from_secs(60));
+
+        let start = Instant::now();
+        throttle(&config).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let file = cache.path().join(LAST_REQUEST_FILE);
+        std::fs::write(
+            &file,
+            (now() - Duration::from_secs(120)).as_millis().to_string(),
+        )
+        .unwrap();
+        let start = Instant::now();
+        throttle(&config).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,94 @@
+use config::Config;
+
+mod bench;
+mod config;
+mod fetch;
+mod http;
+#[cfg(test)]
+mod stand_in;
+mod submit;
+
+const USAGE: &str = "Usage:
+  aoc fetch <year> <day>
+  aoc submit <year> <day> <part> <answer>
+  aoc history <year> <day>
+  aoc bench [--days <d,d,...>] [--save <name>] [--baseline <name>] [--threshold <percent>] [--root <dir>]";
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Err(err) = run(&args) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let config = Config::load()?;
+
+    match args.first().map(String::as_str) {
+        Some("fetch") => {
+            let (year, day) = parse_date(&args[1..])?;
+            let (path, downloaded) = fetch::fetch(&config, year, day)?;
+            if !downloaded {
+                eprintln!("Already cached, not downloading again");
+            }
+            println!("{}", path.display());
+            Ok(())
+        }
+        Some("submit") => {
+            let (year, day) = parse_date(&args[1..])?;
+            let (Some(part), Some(answer)) = (args.get(3), args.get(4)) else {
+                return Err(USAGE.to_string());
+            };
+            let part = match part.as_str() {
+                "1" => 1,
+                "2" => 2,
+                _ => return Err(format!("Part must be 1 or 2, got {part}")),
+            };
+            let outcome = submit::submit(&config, year, day, part, answer.trim())?;
+            println!("{answer}: {outcome}");
+            Ok(())
+        }
+        Some("history") => {
+            let (year, day) = parse_date(&args[1..])?;
+            let history = submit::History::load(&config)?;
+            for part in 1..=2 {
+                for attempt in history.for_part(year, day, part) {
+                    println!(
+                        "part {part}: {} {} at {}",
+                        attempt.answer, attempt.outcome, attempt.timestamp
+                    );
+                }
+            }
+            Ok(())
+        }
+        Some("bench") => {
+            let options = bench::BenchOptions::parse(&args[1..])?;
+            let regressions = bench::run(&config, &options)?;
+            if regressions > 0 {
+                return Err(format!(
+                    "{regressions} phase(s) regressed by more than {}%",
+                    options.threshold
+                ));
+            }
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn parse_date(args: &[String]) -> Result<(u32, u32), String> {
+    let [year, day, ..] = args else {
+        return Err(USAGE.to_string());
+    };
+    let year = year
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid year {year}"))?;
+    let day = day
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid day {day}"))?;
+    if !(1..=25).contains(&day) {
+        return Err(format!("Day must be between 1 and 25, got {day}"));
+    }
+    Ok((year, day))
+}
@@ -0,0 +1,424 @@
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+use crate::http;
+
+const HISTORY_FILE: &str = "submissions.tsv";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Correct,
+    Wrong,
+    TooHigh,
+    TooLow,
+    /// The site asks to wait this many seconds before answering again.
+    RateLimited(u64),
+    /// The part was already solved, or is not unlocked yet.
+    WrongLevel,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Correct => write!(f, "correct"),
+            Outcome::Wrong => write!(f, "wrong"),
+            Outcome::TooHigh => write!(f, "too-high"),
+            Outcome::TooLow => write!(f, "too-low"),
+            Outcome::RateLimited(secs) => write!(f, "rate-limited:{secs}"),
+            Outcome::WrongLevel => write!(f, "wrong-level"),
+        }
+    }
+}
+
+impl Outcome {
+    fn parse(outcome: &str) -> Option<Outcome> {
+        match outcome {
+            "correct" => Some(Outcome::Correct),
+            "wrong" => Some(Outcome::Wrong),
+            "too-high" => Some(Outcome::TooHigh),
+            "too-low" => Some(Outcome::TooLow),
+            "wrong-level" => Some(Outcome::WrongLevel),
+            _ => outcome
+                .strip_prefix("rate-limited:")
+                .and_then(|secs| secs.parse().ok())
+                .map(Outcome::RateLimited),
+        }
+    }
+
+    /// Reads the outcome out of the HTML page returned after posting an answer.
+    fn from_response(body: &str) -> Result<Outcome, String> {
+        if body.contains("That's the right answer") {
+            Ok(Outcome::Correct)
+        } else if body.contains("You gave an answer too recently") {
+            Ok(Outcome::RateLimited(parse_wait(body).unwrap_or(60)))
+        } else if body.contains("You don't seem to be solving the right level") {
+            Ok(Outcome::WrongLevel)
+        } else if body.contains("That's not the right answer") {
+            if body.contains("your answer is too high") {
+                Ok(Outcome::TooHigh)
+            } else if body.contains("your answer is too low") {
+                Ok(Outcome::TooLow)
+            } else {
+                Ok(Outcome::Wrong)
+            }
+        } else {
+            Err("Could not understand the response".to_string())
+        }
+    }
+}
+
+/// Parses "You have 1m 34s left to wait" into seconds.
+fn parse_wait(body: &str) -> Option<u64> {
+    let (before, _) = body.split_once(" left to wait")?;
+    let (_, wait) = before.rsplit_once("You have ")?;
+    wait.split_whitespace().try_fold(0, |total, part| {
+        let (number, unit) = part.split_at(part.find(|c: char| !c.is_ascii_digit())?);
+        let number = number.parse::<u64>().ok()?;
+        match unit {
+            "h" => Some(total + number * 3600),
+            "m" => Some(total + number * 60),
+            "s" => Some(total + number),
+            _ => None,
+        }
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct Attempt {
+    pub year: u32,
+    pub day: u32,
+    pub part: u32,
+    pub answer: String,
+    pub outcome: Outcome,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// Every attempt ever made, stored as tab separated lines in the cache directory.
+pub struct History {
+    path: PathBuf,
+    pub attempts: Vec<Attempt>,
+}
+
+impl History {
+    pub fn load(config: &Config) -> Result<History, String> {
+        let path = config.cache_dir.join(HISTORY_FILE);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(format!("{}: {err}", path.display())),
+        };
+
+        let attempts = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(n, line)| {
+                parse_attempt(line).ok_or(format!("{}:{}: invalid attempt", path.display(), n + 1))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(History { path, attempts })
+    }
+
+    fn record(&mut self, attempt: Attempt) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| format!("{}: {err}", dir.display()))?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|err| format!("{}: {err}", self.path.display()))?;
+        writeln!(
+            file,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            attempt.year,
+            attempt.day,
+            attempt.part,
+            attempt.answer,
+            attempt.outcome,
+            attempt.timestamp
+        )
+        .map_err(|err| format!("{}: {err}", self.path.display()))?;
+
+        self.attempts.push(attempt);
+        Ok(())
+    }
+
+    /// Attempts for one puzzle part, oldest first.
+    pub fn for_part(&self, year: u32, day: u32, part: u32) -> impl Iterator<Item = &Attempt> {
+        self.attempts.iter().filter(move |attempt| {
+            attempt.year == year && attempt.day == day && attempt.part == part
+        })
+    }
+
+    /// Explains why `answer` should not be sent, based on earlier attempts.
+    fn check(&self, year: u32, day: u32, part: u32, answer: &str, now: u64) -> Result<(), String> {
+        if answer.is_empty() || answer.contains(char::is_whitespace) {
+            return Err(format!("Invalid answer {answer:?}"));
+        }
+
+        if let Some(Attempt {
+            outcome: Outcome::RateLimited(secs),
+            timestamp,
+            ..
+        }) = self.attempts.last()
+        {
+            if now < timestamp + secs {
+                return Err(format!(
+                    "Rate limited, wait {}s more",
+                    timestamp + secs - now
+                ));
+            }
+        }
+
+        let mut low: Option<i128> = None;
+        let mut high: Option<i128> = None;
+
+        for attempt in self.for_part(year, day, part) {
+            match attempt.outcome {
+                Outcome::Correct => return Err(format!("Already solved with {}", attempt.answer)),
+                Outcome::Wrong | Outcome::TooHigh | Outcome::TooLow if attempt.answer == answer => {
+                    return Err(format!(
+                        "{answer} was already rejected ({})",
+                        attempt.outcome
+                    ))
+                }
+                Outcome::TooLow => low = low.max(attempt.answer.parse().ok()),
+                Outcome::TooHigh => {
+                    if let Ok(value) = attempt.answer.parse::<i128>() {
+                        high = Some(high.map_or(value, |high| high.min(value)));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Ok(value) = answer.parse::<i128>() {
+            if let Some(low) = low.filter(|&low| value <= low) {
+                return Err(format!("{answer} is not above {low}, which was too low"));
+            }
+            if let Some(high) = high.filter(|&high| value >= high) {
+                return Err(format!("{answer} is not below {high}, which was too high"));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_attempt(line: &str) -> Option<Attempt> {
+    let mut fields = line.split('\t');
+    Some(Attempt {
+        year: fields.next()?.parse().ok()?,
+        day: fields.next()?.parse().ok()?,
+        part: fields.next()?.parse().ok()?,
+        answer: fields.next()?.to_string(),
+        outcome: Outcome::parse(fields.next()?)?,
+        timestamp: fields.next()?.parse().ok()?,
+    })
+}
+
+/// Posts `answer` unless the history already rules it out, and logs the outcome.
+pub fn submit(
+    config: &Config,
+    year: u32,
+    day: u32,
+    part: u32,
+    answer: &str,
+) -> Result<Outcome, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs());
+    let mut history = History::load(config)?;
+    history.check(year, day, part, answer, now)?;
+
+    let level = part.to_string();
+    let response = http::post(
+        config,
+        &format!("/{year}/day/{day}/answer"),
+        &[("level", &level), ("answer", answer)],
+    )?;
+    if response.status != 200 {
+        return Err(format!("Unexpected status {}", response.status));
+    }
+    let outcome = Outcome::from_response(&response.body)?;
+
+    history.record(Attempt {
+        year,
+        day,
+        part,
+        answer: answer.to_string(),
+        outcome: outcome.clone(),
+        timestamp: now,
+    })?;
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stand_in::StandIn;
+
+    fn attempt(answer: &str, outcome: Outcome, timestamp: u64) -> Attempt {
+        Attempt {
+            year: 2023,
+            day: 5,
+            part: 1,
+            answer: answer.to_string(),
+            outcome,
+            timestamp,
+        }
+    }
+
+    fn history(attempts: Vec<Attempt>) -> History {
+        History {
+            path: PathBuf::from(HISTORY_FILE),
+            attempts,
+        }
+    }
+
+    #[test]
+    fn reads_every_response_kind() {
+        let cases = [
+            (
+                "That's the right answer! You are one gold star closer.",
+                Outcome::Correct,
+            ),
+            (
+                "That's not the right answer. If you're stuck, ask for hints.",
+                Outcome::Wrong,
+            ),
+            (
+                "That's not the right answer; your answer is too high.",
+                Outcome::TooHigh,
+            ),
+            (
+                "That's not the right answer; your answer is too low.",
+                Outcome::TooLow,
+            ),
+            (
+                "You gave an answer too recently; you have to wait. You have 1m 34s left to wait.",
+                Outcome::RateLimited(94),
+            ),
+            (
+                "You gave an answer too recently; you have to wait. You have 34s left to wait.",
+                Outcome::RateLimited(34),
+            ),
+            (
+                "You don't seem to be solving the right level.  Did you already complete it?",
+                Outcome::WrongLevel,
+            ),
+        ];
+        for (body, outcome) in cases {
+            assert_eq!(Outcome::from_response(body), Ok(outcome), "{body}");
+        }
+        assert!(Outcome::from_response("<html>Something else</html>").is_err());
+    }
+
+    #[test]
+    fn parses_waits() {
+        assert_eq!(parse_wait("You have 1m 34s left to wait."), Some(94));
+        assert_eq!(parse_wait("You have 34s left to wait."), Some(34));
+        assert_eq!(parse_wait("You have 1h 2m 3s left to wait."), Some(3723));
+        assert_eq!(parse_wait("You have a while left to wait."), None);
+        assert_eq!(parse_wait("Please wait."), None);
+    }
+
+    #[test]
+    fn attempt_lines_round_trip() {
+        for outcome in [
+            Outcome::TooLow,
+            Outcome::RateLimited(60),
+            Outcome::WrongLevel,
+        ] {
+            let line = format!("2023\t5\t1\t42\t{outcome}\t1700000000");
+            let attempt = parse_attempt(&line).unwrap();
+            assert_eq!((attempt.year, attempt.day, attempt.part), (2023, 5, 1));
+            assert_eq!(
+                (attempt.answer.as_str(), attempt.timestamp),
+                ("42", 1700000000)
+            );
+            assert_eq!(attempt.outcome, outcome);
+        }
+        assert!(parse_attempt("2023\t5\t1\t42\tmaybe\t1700000000").is_none());
+        assert!(parse_attempt("2023\t5\t1\t42").is_none());
+    }
+
+    #[test]
+    fn refuses_known_wrong_answer() {
+        let history = history(vec![attempt("42", Outcome::Wrong, 0)]);
+        assert_eq!(
+            history.check(2023, 5, 1, "42", 1000),
+            Err("42 was already rejected (wrong)".to_string())
+        );
+        assert!(history.check(2023, 5, 1, "43", 1000).is_ok());
+        assert!(history.check(2023, 5, 2, "42", 1000).is_ok());
+    }
+
+    #[test]
+    fn refuses_answers_outside_bounds() {
+        let history = history(vec![
+            attempt("100", Outcome::TooLow, 0),
+            attempt("300", Outcome::TooHigh, 0),
+            attempt("120", Outcome::TooLow, 0),
+            attempt("200", Outcome::TooHigh, 0),
+        ]);
+        for answer in ["50", "120", "200", "250"] {
+            assert!(history.check(2023, 5, 1, answer, 1000).is_err(), "{answer}");
+        }
+        assert_eq!(
+            history.check(2023, 5, 1, "110", 1000),
+            Err("110 is not above 120, which was too low".to_string())
+        );
+        assert_eq!(
+            history.check(2023, 5, 1, "250", 1000),
+            Err("250 is not below 200, which was too high".to_string())
+        );
+        assert!(history.check(2023, 5, 1, "121", 1000).is_ok());
+        assert!(history.check(2023, 5, 1, "199", 1000).is_ok());
+    }
+
+    #[test]
+    fn refuses_while_rate_limited() {
+        let history = history(vec![attempt("42", Outcome::RateLimited(60), 1000)]);
+        assert_eq!(
+            history.check(2023, 5, 1, "43", 1030),
+            Err("Rate limited, wait 30s more".to_string())
+        );
+        assert!(history.check(2023, 5, 1, "43", 1060).is_ok());
+    }
+
+    #[test]
+    fn submits_and_records_attempt() {
+        let cache = tempfile::tempdir().unwrap();
+        let server = StandIn::serve(vec![(
+            200,
+            "That's not the right answer; your answer is too low.",
+        )]);
+        let config = server.config(&cache);
+
+        assert_eq!(submit(&config, 2023, 5, 2, "42"), Ok(Outcome::TooLow));
+        // The history now rules out lower answers without asking the server.
+        assert!(submit(&config, 2023, 5, 2, "41").is_err());
+
+        let requests = server.finish();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/2023/day/5/answer");
+        assert_eq!(requests[0].body, "level=2&answer=42");
+
+        let log = std::fs::read_to_string(cache.path().join(HISTORY_FILE)).unwrap();
+        let fields = log
+            .lines()
+            .map(|line| line.split('\t').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0][..5], ["2023", "5", "2", "42", "too-low"]);
+        assert!(fields[0][5].parse::<u64>().is_ok());
+    }
+}
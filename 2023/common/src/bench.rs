@@ -0,0 +1,103 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+const WARM_UP: Duration = Duration::from_millis(200);
+const MEASUREMENT: Duration = Duration::from_secs(1);
+const SAMPLES: u32 = 20;
+
+/// Timing of one phase of a day, per iteration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Measurement {
+    pub day: String,
+    pub phase: String,
+    pub iterations: u64,
+    pub mean_ns: f64,
+    pub median_ns: f64,
+    pub min_ns: f64,
+    pub max_ns: f64,
+}
+
+impl Measurement {
+    /// One JSON object on a single line, as read back by `aoc bench`.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// Criterion-style runner: warms up, then times `SAMPLES` batches whose size is picked so that
+/// the whole measurement takes about `MEASUREMENT`.
+pub struct Bencher {
+    day: String,
+    pub measurements: Vec<Measurement>,
+}
+
+impl Bencher {
+    pub fn new(day: &str) -> Bencher {
+        Bencher {
+            day: day.to_string(),
+            measurements: Vec::new(),
+        }
+    }
+
+    pub fn bench<T, F: FnMut() -> T>(&mut self, phase: &str, mut f: F) {
+        let start = Instant::now();
+        let mut warm_up_iterations = 0u64;
+        while start.elapsed() < WARM_UP {
+            black_box(f());
+            warm_up_iterations += 1;
+        }
+        let estimate = start.elapsed().as_secs_f64() / warm_up_iterations as f64;
+        let batch = ((MEASUREMENT.as_secs_f64() / SAMPLES as f64) / estimate)
+            .ceil()
+            .max(1.0) as u64;
+
+        let mut samples = (0..SAMPLES)
+            .map(|_| {
+                let start = Instant::now();
+                for _ in 0..batch {
+                    black_box(f());
+                }
+                start.elapsed().as_nanos() as f64 / batch as f64
+            })
+            .collect::<Vec<_>>();
+        samples.sort_by(f64::total_cmp);
+
+        let measurement = Measurement {
+            day: self.day.clone(),
+            phase: phase.to_string(),
+            iterations: batch * SAMPLES as u64,
+            mean_ns: samples.iter().sum::<f64>() / samples.len() as f64,
+            median_ns: samples[samples.len() / 2],
+            min_ns: samples[0],
+            max_ns: samples[samples.len() - 1],
+        };
+        println!("{}", measurement.to_json());
+        self.measurements.push(measurement);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_round_trips_with_escaping() {
+        let measurement = Measurement {
+            day: "day5".to_string(),
+            phase: "part \"2\"\\reverse".to_string(),
+            iterations: 20,
+            mean_ns: 1.5,
+            median_ns: 1.25,
+            min_ns: 1.0,
+            max_ns: 2.0,
+        };
+        let json = measurement.to_json();
+        assert!(!json.contains('\n'));
+
+        let parsed: Measurement = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.phase, measurement.phase);
+        assert_eq!((parsed.iterations, parsed.median_ns), (20, 1.25));
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+
+/// A group of consecutive non-blank lines, with trailing whitespace removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block<'a> {
+    /// 1-based line number of the first line of the block in the original input.
+    pub start_line: usize,
+    pub lines: Vec<&'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockError {
+    /// 1-based line number in the original input.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+impl<'a> Block<'a> {
+    /// Checks that every line of the block has the same length as the first one.
+    pub fn check_rectangular(&self) -> Result<(), BlockError> {
+        let width = self.lines[0].chars().count();
+        match self
+            .lines
+            .iter()
+            .position(|line| line.chars().count() != width)
+        {
+            Some(index) => Err(BlockError {
+                line: self.start_line + index,
+                message: format!(
+                    "expected {} columns, found {}",
+                    width,
+                    self.lines[index].chars().count()
+                ),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Splits `input` on blank lines. Accepts `\n`, `\r\n` and `\r` line endings, treats lines made
+/// only of whitespace as blank, and never yields empty blocks.
+pub fn split_blocks(input: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut current: Option<Block> = None;
+
+    for (index, line) in normalized_lines(input).enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blocks.extend(current.take());
+            continue;
+        }
+        current
+            .get_or_insert_with(|| Block {
+                start_line: index + 1,
+                lines: Vec::new(),
+            })
+            .lines
+            .push(line);
+    }
+    blocks.extend(current);
+
+    blocks
+}
+
+/// Like [`split_blocks`], but every block must be a grid with rows of equal length.
+pub fn split_grids(input: &str) -> Result<Vec<Block<'_>>, BlockError> {
+    let blocks = split_blocks(input);
+    for block in &blocks {
+        block.check_rectangular()?;
+    }
+    Ok(blocks)
+}
+
+/// Lines of `input`, splitting on `\r\n`, `\n` or a lone `\r`.
+fn normalized_lines(input: &str) -> impl Iterator<Item = &str> {
+    input
+        .split('\n')
+        .flat_map(|line| line.strip_suffix('\r').unwrap_or(line).split('\r'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(start_line: usize, lines: &[&'static str]) -> Block<'static> {
+        Block {
+            start_line,
+            lines: lines.to_vec(),
+        }
+    }
+
+    #[test]
+    fn splits_on_blank_lines() {
+        let blocks = split_blocks("ab\ncd\n\nef\n");
+        assert_eq!(blocks, vec![block(1, &["ab", "cd"]), block(4, &["ef"])]);
+    }
+
+    #[test]
+    fn accepts_crlf() {
+        let blocks = split_blocks("ab\r\ncd\r\n\r\nef\r\n");
+        assert_eq!(blocks, vec![block(1, &["ab", "cd"]), block(4, &["ef"])]);
+    }
+
+    #[test]
+    fn accepts_lone_cr() {
+        let blocks = split_blocks("ab\rcd\r\ref");
+        assert_eq!(blocks, vec![block(1, &["ab", "cd"]), block(4, &["ef"])]);
+    }
+
+    #[test]
+    fn whitespace_only_lines_separate_blocks() {
+        let blocks = split_blocks("ab  \n \t \nef\t\n");
+        assert_eq!(blocks, vec![block(1, &["ab"]), block(3, &["ef"])]);
+    }
+
+    #[test]
+    fn collapses_repeated_and_trailing_blank_lines() {
+        let blocks = split_blocks("\n\nab\n\n\n\ncd\n\n\n");
+        assert_eq!(blocks, vec![block(3, &["ab"]), block(7, &["cd"])]);
+        assert!(split_blocks("\n \n\r\n").is_empty());
+    }
+
+    #[test]
+    fn ragged_rows_report_their_line() {
+        let err = split_grids("##\n..\n\n###\n#.#\n##\n###\n").unwrap_err();
+        assert_eq!(
+            err,
+            BlockError {
+                line: 6,
+                message: "expected 3 columns, found 2".to_string(),
+            }
+        );
+        assert_eq!(err.to_string(), "line 6: expected 3 columns, found 2");
+    }
+
+    #[test]
+    fn grid_width_counts_chars() {
+        assert!(split_grids("#é#\n###\n").is_ok());
+    }
+}
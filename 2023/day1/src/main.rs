@@ -1,52 +1,293 @@
-use std::collections::HashMap;
+use common::bench::Bencher;
+
+const ENGLISH: [&str; 9] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+const SPANISH: [&str; 9] = [
+    "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve",
+];
+const GERMAN: [&str; 9] = [
+    "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+];
+const FRENCH: [&str; 9] = [
+    "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+];
 
 fn main() {
-    println!("Part1 answer {}", part_one());
-    println!("Part2 answer {}", part_two());
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let options = match args.first().map(String::as_str) {
+        Some("bench") => &args[1..],
+        _ => &args[..],
+    };
+    let (vocabulary, verbose) = match parse_args(options) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!(
+                "Usage: day1 [--lang en|es|de|fr | --words <file>] [--ignore-case] [--verbose]"
+            );
+            std::process::exit(1);
+        }
+    };
+
+    if args.first().map(String::as_str) == Some("bench") {
+        bench(&vocabulary);
+        return;
+    }
+
+    if verbose {
+        println!("Part 1 audit:");
+        audit(include_str!("input"), &Vocabulary::digits_only());
+        println!("Part 2 audit:");
+        audit(include_str!("input"), &vocabulary);
+    }
+
+    let lines = include_str!("input").lines().collect::<Vec<_>>();
+    println!("Part1 answer {}", part_one(&lines));
+    println!("Part2 answer {}", part_two(&lines, &vocabulary));
+}
+
+fn parse_args(args: &[String]) -> Result<(Vocabulary, bool), String> {
+    let mut vocabulary = Vocabulary::builtin("en").unwrap();
+    let mut ignore_case = false;
+    let mut verbose = false;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lang" => {
+                let lang = args.next().ok_or("Missing language")?;
+                vocabulary = Vocabulary::builtin(lang).ok_or(format!("Unknown language {lang}"))?;
+            }
+            "--words" => {
+                let path = args.next().ok_or("Missing word file")?;
+                let contents =
+                    std::fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+                vocabulary = Vocabulary::parse(&contents)?;
+            }
+            "--ignore-case" => ignore_case = true,
+            "--verbose" => verbose = true,
+            _ => return Err(format!("Unknown argument {arg}")),
+        }
+    }
+
+    vocabulary.ignore_case = ignore_case;
+    Ok((vocabulary, verbose))
 }
 
-fn part_one() -> u32 {
-    include_str!("input")
-        .lines()
-        .filter_map(|l| line_to_number(l))
+fn part_one(lines: &[&str]) -> u32 {
+    lines
+        .iter()
+        .filter_map(|l| line_to_number(l, &Vocabulary::digits_only()))
         .sum()
 }
 
-fn part_two() -> u32 {
-    include_str!("input")
-        .lines()
-        .map(|l| replace_str_numbers(l))
-        .filter_map(|l| line_to_number(&l))
+fn part_two(lines: &[&str], vocabulary: &Vocabulary) -> u32 {
+    lines
+        .iter()
+        .filter_map(|l| line_to_number(l, vocabulary))
         .sum()
 }
 
-fn line_to_number(line: &str) -> Option<u32> {
-    let mut digits = line.chars().filter(|c| c.is_digit(10));
-    let first = digits.next()?.to_digit(10)?;
-    let last = digits
-        .last()
-        .unwrap_or_else(|| first.to_string().chars().next().unwrap())
-        .to_digit(10)?;
-    Some(first * 10 + last)
+/// Spelled digits accepted on top of '0'..'9': `words[i]` spells the digit `i + 1`.
+#[derive(Debug, Clone)]
+struct Vocabulary {
+    words: Vec<String>,
+    ignore_case: bool,
+}
+
+impl Vocabulary {
+    fn digits_only() -> Vocabulary {
+        Vocabulary {
+            words: Vec::new(),
+            ignore_case: false,
+        }
+    }
+
+    fn builtin(lang: &str) -> Option<Vocabulary> {
+        let words = match lang {
+            "en" => ENGLISH,
+            "es" => SPANISH,
+            "de" => GERMAN,
+            "fr" => FRENCH,
+            _ => return None,
+        };
+        Some(Vocabulary {
+            words: words.iter().map(|word| word.to_string()).collect(),
+            ignore_case: false,
+        })
+    }
+
+    /// Reads a word file with nine non-empty lines, the first one spelling 1 and the last 9.
+    fn parse(contents: &str) -> Result<Vocabulary, String> {
+        let words = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        if words.len() != 9 {
+            return Err(format!("Expected 9 words, found {}", words.len()));
+        }
+        Ok(Vocabulary {
+            words,
+            ignore_case: false,
+        })
+    }
+}
+
+/// Prints what every line resolved to, flagging the lines without any digit, and returns the sum.
+fn audit(input: &str, vocabulary: &Vocabulary) -> u32 {
+    let mut total = 0;
+    let mut skipped = 0;
+
+    for (number, line) in input.lines().enumerate() {
+        match calibrate(line, vocabulary) {
+            Some(Calibration { first, last, value }) => {
+                println!(
+                    "{:>5}: {:?} @ {} + {:?} @ {} = {:02}",
+                    number + 1,
+                    first.text(line),
+                    first.position,
+                    last.text(line),
+                    last.position,
+                    value
+                );
+                total += value;
+            }
+            None => {
+                println!("{:>5}: SKIPPED, no digit in {:?}", number + 1, line);
+                skipped += 1;
+            }
+        }
+    }
+
+    println!(
+        "Total {total}, {skipped} of {} lines skipped",
+        input.lines().count()
+    );
+    total
+}
+
+/// A digit or spelled digit found in a line. `position` and `len` are in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Match {
+    value: u32,
+    position: usize,
+    len: usize,
+}
+
+impl Match {
+    fn text<'a>(&self, line: &'a str) -> &'a str {
+        &line[self.position..self.position + self.len]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Calibration {
+    first: Match,
+    last: Match,
+    value: u32,
 }
 
-fn replace_str_numbers(input: &str) -> String {
-    let map = HashMap::from([
-        ("one", "o1e"),
-        ("two", "t2o"),
-        ("three", "t3e"),
-        ("four", "f4r"),
-        ("five", "f5e"),
-        ("six", "s6x"),
-        ("seven", "s7n"),
-        ("eight", "e8t"),
-        ("nine", "n9e"),
-    ]);
+fn line_to_number(line: &str, vocabulary: &Vocabulary) -> Option<u32> {
+    calibrate(line, vocabulary).map(|calibration| calibration.value)
+}
+
+/// Finds the first and last digit of `line`. Matches may overlap, so "twone" ends in a 1.
+fn calibrate(line: &str, vocabulary: &Vocabulary) -> Option<Calibration> {
+    let first = line
+        .char_indices()
+        .find_map(|(i, _)| match_at(line, i, vocabulary))?;
+    let last = line
+        .char_indices()
+        .rev()
+        .find_map(|(i, c)| match_ending_at(line, i + c.len_utf8(), vocabulary))?;
+    Some(Calibration {
+        first,
+        last,
+        value: first.value * 10 + last.value,
+    })
+}
+
+/// Returns the digit or word starting at byte `start`, if any.
+fn match_at(line: &str, start: usize, vocabulary: &Vocabulary) -> Option<Match> {
+    let rest = &line[start..];
+    if let Some(digit) = rest.chars().next().and_then(|c| c.to_digit(10)) {
+        return Some(Match {
+            value: digit,
+            position: start,
+            len: 1,
+        });
+    }
+    vocabulary
+        .words
+        .iter()
+        .enumerate()
+        .find_map(|(index, word)| {
+            matched_len(rest.chars(), word.chars(), vocabulary.ignore_case).map(|len| Match {
+                value: index as u32 + 1,
+                position: start,
+                len,
+            })
+        })
+}
 
-    let mut result = String::from(input);
-    for (word, number) in &map {
-        result = result.replace(word, number);
+/// Returns the digit or word ending right before byte `end`, if any.
+fn match_ending_at(line: &str, end: usize, vocabulary: &Vocabulary) -> Option<Match> {
+    let head = &line[..end];
+    if let Some(digit) = head.chars().next_back().and_then(|c| c.to_digit(10)) {
+        return Some(Match {
+            value: digit,
+            position: end - 1,
+            len: 1,
+        });
     }
+    vocabulary
+        .words
+        .iter()
+        .enumerate()
+        .find_map(|(index, word)| {
+            matched_len(
+                head.chars().rev(),
+                word.chars().rev(),
+                vocabulary.ignore_case,
+            )
+            .map(|len| Match {
+                value: index as u32 + 1,
+                position: end - len,
+                len,
+            })
+        })
+}
+
+/// If `text` starts with `word`, returns how many bytes of `text` it covers.
+fn matched_len<T, W>(mut text: T, word: W, ignore_case: bool) -> Option<usize>
+where
+    T: Iterator<Item = char>,
+    W: Iterator<Item = char>,
+{
+    let mut len = 0;
+    for expected in word {
+        let found = text.next()?;
+        let equal = if ignore_case {
+            found.to_lowercase().eq(expected.to_lowercase())
+        } else {
+            found == expected
+        };
+        if !equal {
+            return None;
+        }
+        len += found.len_utf8();
+    }
+    Some(len)
+}
 
-    result
+fn bench(vocabulary: &Vocabulary) {
+    let input = include_str!("input");
+    let lines = input.lines().collect::<Vec<_>>();
+    let mut bencher = Bencher::new("day1");
+    bencher.bench("parse", || input.lines().collect::<Vec<_>>());
+    bencher.bench("part1", || part_one(&lines));
+    bencher.bench("part2", || part_two(&lines, vocabulary));
 }
@@ -1,5 +1,7 @@
 use std::collections::VecDeque;
 
+use common::bench::Bencher;
+
 #[derive(Copy, Clone, Default, Debug)]
 struct Tile {
     x: usize,
@@ -317,6 +319,19 @@ fn part_two(input: &str) -> i32 {
 
 fn main() {
     let input = include_str!("input");
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        bench(input);
+        return;
+    }
+
     println!("Part one: {}", part_one(input));
     println!("Part two: {}", part_two(input));
 }
+
+fn bench(input: &str) {
+    let mut bencher = Bencher::new("day10");
+    bencher.bench("parse", || parse_grid(input));
+    // Both parts mark nodes in a grid of their own, so these phases include parsing.
+    bencher.bench("part1", || part_one(input));
+    bencher.bench("part2", || part_two(input));
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use common::bench::Bencher;
+
 type Point = (usize, usize);
 
 #[derive(Debug, Clone)]
@@ -7,6 +9,7 @@ struct Universe {
     galaxies: Vec<Point>,
     rows: HashMap<usize, u64>,
     cols: HashMap<usize, u64>,
+    // (rows, cols)
     dimensions: (usize, usize),
 }
 
@@ -29,68 +32,292 @@ fn get_galaxies(input: &str) -> Universe {
         galaxies,
         rows,
         cols,
-        dimensions: (input.lines().count(), input.lines().next().unwrap().len()),
+        dimensions: (
+            input.lines().count(),
+            input
+                .lines()
+                .map(|line| line.chars().count())
+                .max()
+                .unwrap_or(0),
+        ),
     }
 }
 
-fn expand_space(space: &Universe, expansion: usize) -> Universe {
-    let row_modifier = calc_expansion_rate(&space.rows, space.dimensions.1, expansion);
-    let col_modifier = calc_expansion_rate(&space.cols, space.dimensions.0, expansion);
+/// Fails if an expanded coordinate does not fit in a `usize`.
+fn expand_space(space: &Universe, expansion: usize) -> Result<Universe, String> {
+    let (height, width) = space.dimensions;
+    let row_modifier = calc_expansion_rate(&space.rows, height, expansion)?;
+    let col_modifier = calc_expansion_rate(&space.cols, width, expansion)?;
 
-    Universe {
-        galaxies: space
-            .galaxies
-            .iter()
-            .map(|&(x, y)| (x + col_modifier[x], y + row_modifier[y]))
-            .collect(),
+    let galaxies = space
+        .galaxies
+        .iter()
+        .map(|&(x, y)| {
+            Some((
+                x.checked_add(col_modifier[x])?,
+                y.checked_add(row_modifier[y])?,
+            ))
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or(OVERFLOW)?;
+
+    Ok(Universe {
+        galaxies,
         ..space.clone()
-    }
+    })
 }
 
-fn calc_expansion_rate(elems: &HashMap<usize, u64>, length: usize, expansion: usize) -> Vec<usize> {
+const OVERFLOW: &str = "Expansion factor too large, coordinates overflow";
+
+fn calc_expansion_rate(
+    elems: &HashMap<usize, u64>,
+    length: usize,
+    expansion: usize,
+) -> Result<Vec<usize>, String> {
     (0..length)
-        .scan(0, |modifier, i| {
+        .scan(Some(0usize), |modifier, i| {
             if elems.get(&i).is_none() {
-                *modifier += expansion;
+                *modifier = modifier.and_then(|modifier| modifier.checked_add(expansion));
             }
-            Some(*modifier)
+            Some(modifier.ok_or(OVERFLOW.to_string()))
         })
         .collect()
 }
 
-fn calculate_total_distance(space: &Universe) -> usize {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Metric {
+    Manhattan,
+    Chebyshev,
+    SquaredEuclidean,
+}
+
+impl Metric {
+    fn from_str(s: &str) -> Option<Metric> {
+        match s {
+            "manhattan" => Some(Metric::Manhattan),
+            "chebyshev" => Some(Metric::Chebyshev),
+            "euclidean2" | "squared-euclidean" => Some(Metric::SquaredEuclidean),
+            _ => None,
+        }
+    }
+
+    /// Distance between two points, widened to `u128`. Only a squared distance between
+    /// coordinates near `usize::MAX` can still overflow, which gives `None`.
+    fn distance(&self, p1: Point, p2: Point) -> Option<u128> {
+        let dx = p1.0.abs_diff(p2.0) as u128;
+        let dy = p1.1.abs_diff(p2.1) as u128;
+        match self {
+            Metric::Manhattan => dx.checked_add(dy),
+            Metric::Chebyshev => Some(dx.max(dy)),
+            Metric::SquaredEuclidean => dx.checked_mul(dx)?.checked_add(dy.checked_mul(dy)?),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PairReport {
+    galaxies: (usize, usize),
+    original: (Point, Point),
+    expanded: (Point, Point),
+    distance: u128,
+}
+
+fn calculate_total_distance(space: &Universe, metric: Metric) -> Result<u128, String> {
     space
         .galaxies
         .iter()
         .enumerate()
-        .fold(0, |sum, (i, &galaxy1)| {
-            sum + space.galaxies[i + 1..]
+        .flat_map(|(i, &galaxy1)| {
+            space.galaxies[i + 1..]
                 .iter()
-                .map(|&galaxy2| manhattan_distance(galaxy1, galaxy2))
-                .sum::<usize>()
+                .map(move |&galaxy2| (galaxy1, galaxy2))
+        })
+        .try_fold(0u128, |sum, (galaxy1, galaxy2)| {
+            sum.checked_add(metric.distance(galaxy1, galaxy2)?)
+        })
+        .ok_or("Total distance overflows".to_string())
+}
+
+/// Returns the `k` closest and the `k` farthest galaxy pairs after expansion.
+fn closest_and_farthest(
+    space: &Universe,
+    expanded: &Universe,
+    metric: Metric,
+    k: usize,
+) -> Result<(Vec<PairReport>, Vec<PairReport>), String> {
+    let mut pairs = (0..expanded.galaxies.len())
+        .flat_map(|i| (i + 1..expanded.galaxies.len()).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            Some(PairReport {
+                galaxies: (i + 1, j + 1),
+                original: (space.galaxies[i], space.galaxies[j]),
+                expanded: (expanded.galaxies[i], expanded.galaxies[j]),
+                distance: metric.distance(expanded.galaxies[i], expanded.galaxies[j])?,
+            })
         })
+        .collect::<Option<Vec<_>>>()
+        .ok_or("Pair distance overflows".to_string())?;
+    pairs.sort_by_key(|pair| (pair.distance, pair.galaxies));
+
+    // The two lists overlap when there are fewer than `2k` pairs.
+    let k = k.min(pairs.len());
+    let farthest = pairs[pairs.len() - k..].iter().rev().cloned().collect();
+    pairs.truncate(k);
+
+    Ok((pairs, farthest))
 }
 
-fn manhattan_distance(p1: Point, p2: Point) -> usize {
-    (p1.0 as i32 - p2.0 as i32).abs() as usize + (p1.1 as i32 - p2.1 as i32).abs() as usize
+fn print_pairs(title: &str, pairs: &[PairReport]) {
+    println!("{title}:");
+    for pair in pairs {
+        println!(
+            "  #{} {:?} -> {:?}  #{} {:?} -> {:?}  distance {}",
+            pair.galaxies.0,
+            pair.original.0,
+            pair.expanded.0,
+            pair.galaxies.1,
+            pair.original.1,
+            pair.expanded.1,
+            pair.distance
+        );
+    }
 }
 
-fn part_one(input: &str) -> usize {
-    let space = get_galaxies(input);
-    let expanded_space = expand_space(&space, 1);
+fn part_one(space: &Universe) -> u128 {
+    let expanded_space = expand_space(space, 1).unwrap();
+
+    calculate_total_distance(&expanded_space, Metric::Manhattan).unwrap()
+}
+
+fn part_two(space: &Universe) -> u128 {
+    let expanded_space = expand_space(space, 999999).unwrap();
 
-    calculate_total_distance(&expanded_space)
+    calculate_total_distance(&expanded_space, Metric::Manhattan).unwrap()
 }
 
-fn part_two(input: &str) -> usize {
+/// Usage: `day11 pairs <k> [factor] [manhattan|chebyshev|euclidean2]`
+fn query_pairs(input: &str, args: &[String]) -> Result<(), String> {
+    let k = args
+        .first()
+        .ok_or("Missing k")?
+        .parse::<usize>()
+        .map_err(|_| "Invalid k")?;
+    let factor = match args.get(1) {
+        Some(factor) => factor
+            .parse::<usize>()
+            .map_err(|_| "Invalid expansion factor")?,
+        None => 2,
+    };
+    if factor == 0 {
+        return Err("Expansion factor must be at least 1".to_string());
+    }
+    let metric = match args.get(2) {
+        Some(metric) => Metric::from_str(metric).ok_or(format!("Unknown metric {metric}"))?,
+        None => Metric::Manhattan,
+    };
+
     let space = get_galaxies(input);
-    let expanded_space = expand_space(&space, 999999);
+    let expanded_space = expand_space(&space, factor - 1)?;
+    let (closest, farthest) = closest_and_farthest(&space, &expanded_space, metric, k)?;
 
-    calculate_total_distance(&expanded_space)
+    println!("Factor: {factor}, metric: {metric:?}");
+    println!(
+        "Total: {}",
+        calculate_total_distance(&expanded_space, metric)?
+    );
+    print_pairs("Closest", &closest);
+    print_pairs("Farthest", &farthest);
+    Ok(())
 }
 
 fn main() {
     let input = include_str!("input");
-    println!("Part One: {}", part_one(input));
-    println!("Part Two: {}", part_two(input));
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if args.first().map(String::as_str) == Some("bench") {
+        let space = get_galaxies(input);
+        let mut bencher = Bencher::new("day11");
+        bencher.bench("parse", || get_galaxies(input));
+        bencher.bench("part1", || part_one(&space));
+        bencher.bench("part2", || part_two(&space));
+        return;
+    }
+
+    if args.first().map(String::as_str) == Some("pairs") {
+        if let Err(err) = query_pairs(input, &args[1..]) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let space = get_galaxies(input);
+    println!("Part One: {}", part_one(&space));
+    println!("Part Two: {}", part_two(&space));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3 rows, 6 columns: row 1 and columns 1, 3 and 4 are empty.
+    const WIDE: &str = "#....#\n......\n..#...\n";
+    // 6 rows, 3 columns: rows 1, 2 and 4 and column 1 are empty.
+    const TALL: &str = "#..\n...\n...\n..#\n...\n#..\n";
+
+    #[test]
+    fn expands_wide_map() {
+        let space = get_galaxies(WIDE);
+        assert_eq!(space.dimensions, (3, 6));
+
+        let expanded = expand_space(&space, 1).unwrap();
+        assert_eq!(expanded.galaxies, vec![(0, 0), (8, 0), (3, 3)]);
+        assert_eq!(part_one(&space), 22);
+    }
+
+    #[test]
+    fn expands_tall_map() {
+        let space = get_galaxies(TALL);
+        assert_eq!(space.dimensions, (6, 3));
+
+        let expanded = expand_space(&space, 1).unwrap();
+        assert_eq!(expanded.galaxies, vec![(0, 0), (3, 5), (0, 8)]);
+        assert_eq!(part_one(&space), 22);
+    }
+
+    #[test]
+    fn huge_factor_is_an_error() {
+        let space = get_galaxies(WIDE);
+        assert!(expand_space(&space, usize::MAX).is_err());
+
+        let expanded = expand_space(&space, usize::MAX / 4).unwrap();
+        assert!(calculate_total_distance(&expanded, Metric::Manhattan).is_ok());
+
+        let far = (usize::MAX, usize::MAX);
+        assert!(Metric::SquaredEuclidean
+            .distance((0, 0), (usize::MAX, 0))
+            .is_some());
+        assert_eq!(Metric::SquaredEuclidean.distance((0, 0), far), None);
+    }
+    #[test]
+    fn pair_lists_overlap_when_k_exceeds_half_the_pairs() {
+        let space = get_galaxies(WIDE);
+        let expanded = expand_space(&space, 1).unwrap();
+        let distances = |pairs: &[PairReport]| {
+            pairs
+                .iter()
+                .map(|pair| (pair.galaxies, pair.distance))
+                .collect::<Vec<_>>()
+        };
+
+        let (closest, farthest) =
+            closest_and_farthest(&space, &expanded, Metric::Manhattan, 2).unwrap();
+        assert_eq!(distances(&closest), vec![((1, 3), 6), ((1, 2), 8)]);
+        assert_eq!(distances(&farthest), vec![((2, 3), 8), ((1, 2), 8)]);
+
+        let (closest, farthest) =
+            closest_and_farthest(&space, &expanded, Metric::Manhattan, 5).unwrap();
+        assert_eq!(closest.len(), 3);
+        assert_eq!(farthest.len(), 3);
+    }
 }
@@ -1,38 +1,145 @@
+use common::bench::Bencher;
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::Rng;
+
+mod nonogram;
+
+const USAGE: &str = "Usage: day12 [enumerate <pattern> <sizes> | sample <pattern> <sizes> [amount] | verify [max_unknowns] \
+                     | nonogram <rows> <cols> | unfold [factor] | bench]";
+
 fn main() {
     let input = include_str!("input");
-    println!("Part one: {}", solve(input, |s| s.to_owned() + ".", |v| v).unwrap());
-    println!(
-        "Part two: {}",
-        solve(
-            input,
-            |s| (0..5).map(|_| s).collect::<Vec<_>>().join("?") + ".",
-            |v| unfold_sizes(v, 5)
-        ).unwrap()
-    );
-}
-
-fn solve<F1, F2>(input: &str, transform_pattern: F1, transform_sizes: F2) -> Result<usize, &'static str>
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if let Err(err) = run(input, &args) {
+        eprintln!("{err}");
+        eprintln!("{USAGE}");
+        std::process::exit(1);
+    }
+}
+
+fn run(input: &str, args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("enumerate") => {
+            let line = row_arg(args)?;
+            let (pattern, sizes) = parse_line(&line)?;
+            let mut total = 0;
+            for arrangement in arrangements(pattern, &sizes) {
+                println!("{arrangement}");
+                total += 1;
+            }
+            println!("Total: {total}");
+        }
+        Some("sample") => {
+            let line = row_arg(args)?;
+            let (pattern, sizes) = parse_line(&line)?;
+            let amount = number_arg(args.get(3), 1, "amount")?;
+            let mut rng = rand::thread_rng();
+            for _ in 0..amount {
+                match sample_arrangement(pattern, &sizes, &mut rng) {
+                    Some(arrangement) => println!("{arrangement}"),
+                    None => println!("No valid arrangement"),
+                }
+            }
+        }
+        Some("verify") => {
+            let max_unknowns = number_arg(args.get(1), 12, "max_unknowns")?;
+            println!("Mismatches: {}", verify(input, max_unknowns)?);
+        }
+        Some("nonogram") => {
+            let [_, rows, cols, ..] = args else {
+                return Err("Missing row or column clue file".to_string());
+            };
+            let rows = std::fs::read_to_string(rows).map_err(|err| format!("{rows}: {err}"))?;
+            let cols = std::fs::read_to_string(cols).map_err(|err| format!("{cols}: {err}"))?;
+            let puzzle = nonogram::Puzzle::parse(&rows, &cols)?;
+            match puzzle.solve() {
+                nonogram::Solution::None => println!("No solution"),
+                nonogram::Solution::Unique(grid) => println!("{}", nonogram::render(&grid)),
+                nonogram::Solution::Multiple(first, second) => {
+                    println!("Multiple solutions, for example:");
+                    println!("{}\n", nonogram::render(&first));
+                    println!("{}", nonogram::render(&second));
+                }
+            }
+        }
+        Some("bench") => {
+            let rows = parse_rows(input)?;
+            let mut bencher = Bencher::new("day12");
+            bencher.bench("parse", || parse_rows(input));
+            bencher.bench("part1", || solve(&rows, |s| s.to_owned(), |v| v));
+            bencher.bench("part2", || solve_unfolded(&rows, 5));
+        }
+        Some("unfold") => {
+            let factor = number_arg(args.get(1), 5, "factor")?;
+            println!(
+                "Factor {factor}: {}",
+                solve_unfolded(&parse_rows(input)?, factor)
+            );
+        }
+        Some(command) => return Err(format!("Unknown command {command}")),
+        None => {
+            let rows = parse_rows(input)?;
+            println!("Part one: {}", solve(&rows, |s| s.to_owned(), |v| v));
+            println!("Part two: {}", solve_unfolded(&rows, 5));
+        }
+    }
+    Ok(())
+}
+
+/// Joins the `<pattern> <sizes>` arguments of `enumerate` and `sample` back into a row.
+fn row_arg(args: &[String]) -> Result<String, String> {
+    let [_, pattern, sizes, ..] = args else {
+        return Err("Missing pattern or sizes".to_string());
+    };
+    Ok(format!("{pattern} {sizes}"))
+}
+
+fn number_arg(arg: Option<&String>, default: usize, name: &str) -> Result<usize, String> {
+    match arg {
+        Some(n) => n.parse().map_err(|_| format!("Invalid {name} {n}")),
+        None => Ok(default),
+    }
+}
+
+/// Splits a row such as `???.### 1,1,3` into its pattern and group sizes.
+fn parse_line(line: &str) -> Result<(&str, Vec<usize>), &'static str> {
+    let parts = line.split_whitespace().collect::<Vec<_>>();
+    let pattern = parts.first().ok_or("Pattern not found")?;
+    let sizes = parts
+        .get(1)
+        .ok_or("Sizes not found")?
+        .split(',')
+        .map(|s| s.parse::<usize>().map_err(|_| "Invalid size"))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((pattern, sizes))
+}
+
+type Row<'a> = (&'a str, Vec<usize>);
+
+fn parse_rows(input: &str) -> Result<Vec<Row<'_>>, &'static str> {
+    input.lines().map(parse_line).collect()
+}
+
+fn solve<F1, F2>(rows: &[Row], transform_pattern: F1, transform_sizes: F2) -> BigUint
 where
     F1: Fn(&str) -> String,
     F2: Fn(Vec<usize>) -> Vec<usize>,
 {
-    input
-        .lines()
-        .map(|line| {
-            let parts = line.split_whitespace().collect::<Vec<_>>();
-            let pattern = transform_pattern(parts.get(0).ok_or("Pattern not found")?);
-            let sizes = parts.get(1).ok_or("Sizes not found")?
-                .split(',')
-                .map(|s| s.parse::<usize>().map_err(|_| "Invalid size"))
-                .collect::<Result<Vec<_>, _>>()?;
-            let transformed_sizes = transform_sizes(sizes);
-            Ok((pattern, transformed_sizes))
-        })
-        .try_fold(0, |acc, line_result| {
-            let (pattern, sizes) = line_result?;
-            let mut memoization = vec![vec![None; sizes.len()]; pattern.len()];
-            Ok(acc + calculate_arrangements(0, 0, &pattern, &sizes, &mut memoization))
-        })
+    rows.iter().fold(BigUint::zero(), |acc, (pattern, sizes)| {
+        let pattern = transform_pattern(pattern);
+        let sizes = transform_sizes(sizes.clone());
+        acc + calculate_arrangements(pattern.as_bytes(), &sizes)
+    })
+}
+
+fn solve_unfolded(rows: &[Row], factor: usize) -> BigUint {
+    solve(
+        rows,
+        |s| vec![s; factor].join("?"),
+        |v| unfold_sizes(v, factor),
+    )
 }
 
 /// Returns a vector containing `amount` copies of the elements in `vector`.
@@ -44,62 +151,231 @@ fn unfold_sizes<T: Clone>(vector: Vec<T>, amount: usize) -> Vec<T> {
     result
 }
 
-fn calculate_arrangements(
-    index: usize,
-    group_index: usize,
-    pattern: &str,
-    sizes: &Vec<usize>,
-    memoization: &mut Vec<Vec<Option<usize>>>,
-) -> usize {
-    // Check if we have processed all groups. If so, verify if the rest of the pattern is valid.
-    if group_index == sizes.len() {
-        return if pattern[index..].contains('#') {
-            0 // Invalid pattern as '#' found after processing all groups.
-        } else {
-            1 // Valid pattern as no '#' found after processing all groups.
-        };
+fn calculate_arrangements(pattern: &[u8], sizes: &[usize]) -> BigUint {
+    arrangement_table(pattern, sizes)
+        .swap_remove(0)
+        .swap_remove(0)
+}
+
+/// Builds `ways[index][group_index]`: the number of ways to place `sizes[group_index..]` in
+/// `pattern[index..]`. Filled bottom-up, so long unfolded patterns never recurse.
+///
+/// The pattern is extended with a trailing '.', so every group is followed by an operational
+/// spring.
+fn arrangement_table(pattern: &[u8], sizes: &[usize]) -> Vec<Vec<BigUint>> {
+    let pattern = [pattern, b"."].concat();
+    let length = pattern.len();
+
+    // Length of the run of non-'.' springs starting at each index.
+    let mut run = vec![0; length + 1];
+    for index in (0..length).rev() {
+        if pattern[index] != b'.' {
+            run[index] = run[index + 1] + 1;
+        }
+    }
+
+    let mut ways = vec![vec![BigUint::zero(); sizes.len() + 1]; length + 1];
+
+    // With every group placed, the rest is valid only if it contains no '#'.
+    ways[length][sizes.len()] = BigUint::one();
+    for index in (0..length).rev() {
+        if pattern[index] != b'#' {
+            ways[index][sizes.len()] = ways[index + 1][sizes.len()].clone();
+        }
+    }
+
+    for group_index in (0..sizes.len()).rev() {
+        let size = sizes[group_index];
+        for index in (0..length).rev() {
+            let mut result = BigUint::zero();
+
+            // If the current spring is not '#', try skipping it.
+            if pattern[index] != b'#' {
+                result += &ways[index + 1][group_index];
+            }
+
+            // Place the group here if it fits before a spring that isn't '#'.
+            if run[index] >= size && index + size < length && pattern[index + size] != b'#' {
+                result += &ways[index + size + 1][group_index + 1];
+            }
+
+            ways[index][group_index] = result;
+        }
+    }
+
+    ways
+}
+
+/// Lazily yields every concrete arrangement of a row, in lexicographic order with '.' < '#'.
+///
+/// Branches with no arrangements are never entered, so every step makes progress towards the
+/// next arrangement.
+struct Arrangements {
+    pattern: Vec<u8>,
+    sizes: Vec<usize>,
+    ways: Vec<Vec<BigUint>>,
+    stack: Vec<(usize, usize, String)>,
+}
+
+fn arrangements(pattern: &str, sizes: &[usize]) -> Arrangements {
+    Arrangements {
+        pattern: pattern.as_bytes().to_vec(),
+        sizes: sizes.to_vec(),
+        ways: arrangement_table(pattern.as_bytes(), sizes),
+        stack: vec![(0, 0, String::new())],
     }
+}
+
+impl Arrangements {
+    /// The states reachable from `(index, group_index)`: skipping the current spring, or placing
+    /// the current group here. Each comes with the springs it appends and its arrangement count.
+    fn successors(
+        &self,
+        index: usize,
+        group_index: usize,
+    ) -> Vec<(usize, usize, String, &BigUint)> {
+        let mut result = Vec::new();
+        let size = self.sizes[group_index];
+        let spring = |i: usize| self.pattern.get(i).copied().unwrap_or(b'.');
+
+        if spring(index) != b'#' {
+            result.push((
+                index + 1,
+                group_index,
+                ".".to_string(),
+                &self.ways[index + 1][group_index],
+            ));
+        }
 
-    // Return 0 if we've reached the end of the pattern without processing all groups.
-    if index >= pattern.len() {
-        return 0;
+        if index + size <= self.pattern.len()
+            && (index..index + size).all(|i| spring(i) != b'.')
+            && spring(index + size) != b'#'
+        {
+            result.push((
+                index + size + 1,
+                group_index + 1,
+                "#".repeat(size) + ".",
+                &self.ways[index + size + 1][group_index + 1],
+            ));
+        }
+
+        result.retain(|&(.., count)| !count.is_zero());
+        result
     }
 
-    // Return the cached result if this state has already been computed.
-    if let Some(result) = memoization[index][group_index] {
-        return result;
+    /// Pads a finished prefix with '.' up to the length of the pattern.
+    fn finish(&self, mut arrangement: String) -> String {
+        arrangement.truncate(self.pattern.len());
+        let padding = self.pattern.len() - arrangement.len();
+        arrangement + &".".repeat(padding)
     }
+}
 
-    // Initialize the result for this state.
-    let mut result = 0;
+impl Iterator for Arrangements {
+    type Item = String;
 
-    // Get the character at the current index in the pattern.
-    let current_char = pattern.chars().nth(index).unwrap();
+    fn next(&mut self) -> Option<String> {
+        while let Some((index, group_index, prefix)) = self.stack.pop() {
+            if self.ways[index][group_index].is_zero() {
+                continue;
+            }
 
-    // If the current character is not '#', try skipping it and moving to the next character.
-    if current_char != '#' {
-        result += calculate_arrangements(index + 1, group_index, pattern, sizes, memoization);
+            if group_index == self.sizes.len() {
+                return Some(self.finish(prefix));
+            }
+
+            // Pushed in reverse so that the '.' branch is explored first.
+            let successors = self
+                .successors(index, group_index)
+                .into_iter()
+                .rev()
+                .map(|(next_index, next_group, springs, _)| {
+                    (next_index, next_group, prefix.clone() + &springs)
+                })
+                .collect::<Vec<_>>();
+            self.stack.extend(successors);
+        }
+        None
     }
+}
 
-    // Check if it's possible to place the current group at this index.
-    if index + sizes[group_index] <= pattern.len()
-        // Ensure all characters in the group range are not '.'.
-        && pattern[index..index + sizes[group_index]].chars().all(|c| c != '.')
-        // Ensure the character following the group is not '#'.
-        && pattern.chars().nth(index + sizes[group_index]).unwrap_or('.') != '#'
-    {
-        // If conditions are met, place the group and move to the next group.
-        result += calculate_arrangements(
-            index + sizes[group_index] + 1,
-            group_index + 1,
-            pattern,
-            sizes,
-            memoization,
-        );
+/// Draws an arrangement uniformly at random, choosing each branch with probability
+/// proportional to the number of arrangements below it.
+fn sample_arrangement<R: Rng>(pattern: &str, sizes: &[usize], rng: &mut R) -> Option<String> {
+    let row = arrangements(pattern, sizes);
+    let (mut index, mut group_index) = (0, 0);
+    let mut result = String::new();
+
+    if row.ways[0][0].is_zero() {
+        return None;
     }
 
-    // Cache the result for the current state before returning.
-    memoization[index][group_index] = Some(result);
+    while group_index < row.sizes.len() {
+        let successors = row.successors(index, group_index);
+        let total = successors.iter().map(|&(.., count)| count).sum::<BigUint>();
+        let mut pick = rng.gen_biguint_below(&total);
+        for (next_index, next_group, springs, count) in successors {
+            if &pick < count {
+                index = next_index;
+                group_index = next_group;
+                result += &springs;
+                break;
+            }
+            pick -= count;
+        }
+    }
 
-    result
+    Some(row.finish(result))
+}
+
+/// Brute-forces every row with at most `max_unknowns` '?' and compares the number of valid
+/// assignments with both `calculate_arrangements` and the `arrangements` enumeration.
+fn verify(input: &str, max_unknowns: usize) -> Result<usize, &'static str> {
+    let mut mismatches = 0;
+    for line in input.lines() {
+        let (pattern, sizes) = parse_line(line)?;
+        let unknowns = pattern.bytes().filter(|&c| c == b'?').count();
+        if unknowns > max_unknowns {
+            continue;
+        }
+
+        let brute_force = (0..1usize << unknowns)
+            .filter(|mask| {
+                let mut bit = 0;
+                let candidate = pattern
+                    .chars()
+                    .map(|c| match c {
+                        '?' => {
+                            bit += 1;
+                            if mask & (1 << (bit - 1)) != 0 {
+                                '#'
+                            } else {
+                                '.'
+                            }
+                        }
+                        c => c,
+                    })
+                    .collect::<String>();
+                group_sizes(&candidate) == sizes
+            })
+            .count();
+        let counted = calculate_arrangements(pattern.as_bytes(), &sizes);
+        let enumerated = arrangements(pattern, &sizes).count();
+
+        if BigUint::from(brute_force) != counted || brute_force != enumerated {
+            println!(
+                "{line}: brute force {brute_force}, counted {counted}, enumerated {enumerated}"
+            );
+            mismatches += 1;
+        }
+    }
+    Ok(mismatches)
+}
+
+fn group_sizes(arrangement: &str) -> Vec<usize> {
+    arrangement
+        .split('.')
+        .filter(|group| !group.is_empty())
+        .map(str::len)
+        .collect()
 }
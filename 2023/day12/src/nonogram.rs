@@ -0,0 +1,249 @@
+use num_traits::Zero;
+
+use crate::calculate_arrangements;
+
+const FILLED: u8 = b'#';
+const EMPTY: u8 = b'.';
+const UNKNOWN: u8 = b'?';
+
+/// A picture puzzle: `rows[i]` and `cols[j]` are the run lengths of filled cells, in order.
+#[derive(Debug)]
+pub struct Puzzle {
+    rows: Vec<Vec<usize>>,
+    cols: Vec<Vec<usize>>,
+}
+
+/// Grid of cells using the spring alphabet: '#' filled, '.' empty, '?' unknown.
+pub type Grid = Vec<Vec<u8>>;
+
+#[derive(Debug)]
+pub enum Solution {
+    None,
+    Unique(Grid),
+    /// Two distinct solutions; there may be more.
+    Multiple(Grid, Grid),
+}
+
+impl Puzzle {
+    /// Reads the row clues and column clues, one line per row/column with comma separated run
+    /// lengths. An empty line or `0` stands for a line with no filled cells.
+    pub fn parse(rows: &str, cols: &str) -> Result<Puzzle, String> {
+        let rows = parse_clues(rows)?;
+        let cols = parse_clues(cols)?;
+
+        if rows.iter().any(|clue| clue_length(clue) > cols.len()) {
+            return Err("A row clue does not fit in the grid width".to_string());
+        }
+        if cols.iter().any(|clue| clue_length(clue) > rows.len()) {
+            return Err("A column clue does not fit in the grid height".to_string());
+        }
+        let filled_rows = rows.iter().flatten().sum::<usize>();
+        let filled_cols = cols.iter().flatten().sum::<usize>();
+        if filled_rows != filled_cols {
+            return Err(format!(
+                "Rows fill {filled_rows} cells but columns fill {filled_cols}"
+            ));
+        }
+
+        Ok(Puzzle { rows, cols })
+    }
+
+    pub fn solve(&self) -> Solution {
+        let grid = vec![vec![UNKNOWN; self.cols.len()]; self.rows.len()];
+        let mut solutions = Vec::new();
+        self.search(grid, &mut solutions);
+
+        match solutions.len() {
+            0 => Solution::None,
+            1 => Solution::Unique(solutions.remove(0)),
+            _ => {
+                let second = solutions.remove(1);
+                Solution::Multiple(solutions.remove(0), second)
+            }
+        }
+    }
+
+    /// Propagates line constraints, then branches on the first unknown cell. Stops as soon as
+    /// two solutions are known.
+    fn search(&self, mut grid: Grid, solutions: &mut Vec<Grid>) {
+        if !self.propagate(&mut grid) {
+            return;
+        }
+
+        let unknown = grid.iter().enumerate().find_map(|(row, line)| {
+            line.iter()
+                .position(|&c| c == UNKNOWN)
+                .map(|col| (row, col))
+        });
+
+        match unknown {
+            None => solutions.push(grid),
+            Some((row, col)) => {
+                for value in [FILLED, EMPTY] {
+                    if solutions.len() >= 2 {
+                        return;
+                    }
+                    let mut guess = grid.clone();
+                    guess[row][col] = value;
+                    self.search(guess, solutions);
+                }
+            }
+        }
+    }
+
+    /// Solves lines one at a time until nothing changes. Returns false on a contradiction.
+    fn propagate(&self, grid: &mut Grid) -> bool {
+        let mut dirty_rows = vec![true; self.rows.len()];
+        let mut dirty_cols = vec![true; self.cols.len()];
+
+        while dirty_rows.contains(&true) || dirty_cols.contains(&true) {
+            for row in 0..self.rows.len() {
+                if !std::mem::take(&mut dirty_rows[row]) {
+                    continue;
+                }
+                let Some(changed) = solve_line(&mut grid[row], &self.rows[row]) else {
+                    return false;
+                };
+                changed.into_iter().for_each(|col| dirty_cols[col] = true);
+            }
+
+            for col in 0..self.cols.len() {
+                if !std::mem::take(&mut dirty_cols[col]) {
+                    continue;
+                }
+                let mut line = grid.iter().map(|cells| cells[col]).collect::<Vec<_>>();
+                let Some(changed) = solve_line(&mut line, &self.cols[col]) else {
+                    return false;
+                };
+                for row in changed {
+                    grid[row][col] = line[row];
+                    dirty_rows[row] = true;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// Fixes every unknown cell of `line` that has the same value in all arrangements matching
+/// `clue`. Returns the indices that changed, or `None` if the line has no arrangement at all.
+fn solve_line(line: &mut [u8], clue: &[usize]) -> Option<Vec<usize>> {
+    if calculate_arrangements(line, clue).is_zero() {
+        return None;
+    }
+
+    let mut changed = Vec::new();
+    for index in 0..line.len() {
+        if line[index] != UNKNOWN {
+            continue;
+        }
+
+        line[index] = FILLED;
+        let can_fill = !calculate_arrangements(line, clue).is_zero();
+        line[index] = EMPTY;
+        let can_empty = !calculate_arrangements(line, clue).is_zero();
+
+        line[index] = match (can_fill, can_empty) {
+            (true, false) => FILLED,
+            (false, true) => EMPTY,
+            _ => UNKNOWN,
+        };
+        if line[index] != UNKNOWN {
+            changed.push(index);
+        }
+    }
+    Some(changed)
+}
+
+fn parse_clues(input: &str) -> Result<Vec<Vec<usize>>, String> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(number, line)| {
+            line.split(',')
+                .map(str::trim)
+                .filter(|size| !size.is_empty() && *size != "0")
+                .map(|size| {
+                    size.parse::<usize>()
+                        .map_err(|_| format!("Line {}: invalid clue {size:?}", number + 1))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Minimum number of cells needed to fit the runs of a clue.
+fn clue_length(clue: &[usize]) -> usize {
+    clue.iter().sum::<usize>() + clue.len().saturating_sub(1)
+}
+
+pub fn render(grid: &Grid) -> String {
+    grid.iter()
+        .map(|line| String::from_utf8_lossy(line).into_owned())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(rows: &str, cols: &str) -> Solution {
+        Puzzle::parse(rows, cols).unwrap().solve()
+    }
+
+    #[test]
+    fn solves_unique_puzzle() {
+        match solve("1\n3\n1\n", "1\n3\n1\n") {
+            Solution::Unique(grid) => assert_eq!(render(&grid), ".#.\n###\n.#."),
+            other => panic!("expected a unique solution, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn finds_two_solutions_of_ambiguous_puzzle() {
+        match solve("1\n1\n", "1\n1\n") {
+            Solution::Multiple(first, second) => {
+                let mut found = [render(&first), render(&second)];
+                found.sort();
+                assert_eq!(found, ["#.\n.#", ".#\n#."]);
+            }
+            other => panic!("expected several solutions, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn contradictory_puzzle_has_no_solution() {
+        // The first row can only be "#.#", but the last column must stay empty.
+        assert!(matches!(solve("1,1\n0\n0\n", "1\n1\n0\n"), Solution::None));
+    }
+
+    #[test]
+    fn solve_line_fixes_forced_cells() {
+        let mut line = b"??#??".to_vec();
+        assert_eq!(solve_line(&mut line, &[3]), Some(vec![]));
+        let mut line = b"?#???".to_vec();
+        assert_eq!(solve_line(&mut line, &[3]), Some(vec![2, 4]));
+        assert_eq!(line, b"?##?.");
+        assert_eq!(solve_line(&mut b"#.#".to_vec(), &[3]), None);
+    }
+
+    #[test]
+    fn parses_empty_and_zero_clues() {
+        assert_eq!(
+            parse_clues("1, 2\n\n0\n 3 \n").unwrap(),
+            vec![vec![1, 2], vec![], vec![], vec![3]]
+        );
+        assert_eq!(
+            parse_clues("1\n2,x\n").unwrap_err(),
+            "Line 2: invalid clue \"x\""
+        );
+    }
+
+    #[test]
+    fn rejects_clues_that_do_not_fit() {
+        assert!(Puzzle::parse("3\n", "1\n1\n").is_err());
+        assert!(Puzzle::parse("1\n1\n", "1\n0\n").is_err());
+    }
+}
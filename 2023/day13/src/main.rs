@@ -1,120 +1,269 @@
+use common::bench::Bencher;
+use common::blocks::{split_grids, Block};
+
 fn main() {
     let input = include_str!("input");
-    println!("Part one: {}", part_one(input));
-    println!("Part two: {}", part_two(input));
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if let Err(err) = run(input, &args) {
+        eprintln!("{err}");
+        eprintln!("Usage: day13 [report [tolerance] | bench]");
+        std::process::exit(1);
+    }
 }
 
-fn part_one(input: &str) -> usize {
-    input
-        .split("\n\n")
-        .map(parse_pattern)
-        .map(summarize_reflections)
-        .sum()
+fn run(input: &str, args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("bench") => {
+            let patterns = parse_patterns(input)?;
+            let mut bencher = Bencher::new("day13");
+            bencher.bench("parse", || parse_patterns(input));
+            bencher.bench("part1", || part_one(&patterns));
+            bencher.bench("part2", || part_two(&patterns));
+        }
+        Some("report") => {
+            let tolerance = match args.get(1) {
+                Some(n) => n.parse().map_err(|_| format!("Invalid tolerance {n}"))?,
+                None => 1,
+            };
+            report(input, tolerance)?;
+        }
+        Some(command) => return Err(format!("Unknown command {command}")),
+        None => {
+            let patterns = parse_patterns(input)?;
+            println!("Part one: {}", part_one(&patterns)?);
+            println!("Part two: {}", part_two(&patterns)?);
+        }
+    }
+    Ok(())
 }
 
-fn part_two(input: &str) -> usize {
-    input
-        .split("\n\n")
-        .map(parse_pattern)
-        .map(summarize_reflections_with_smudge)
-        .sum()
+/// Prints every mirror line of every pattern with up to `tolerance` mismatches.
+fn report(input: &str, tolerance: usize) -> Result<(), String> {
+    for (index, pattern) in parse_patterns(input)?.iter().enumerate() {
+        let reflections = find_reflections(pattern, tolerance);
+        if reflections.is_empty() {
+            println!("Pattern {}: no reflection", index + 1);
+        }
+        for reflection in reflections {
+            println!(
+                "Pattern {}: {:?} at {}, {} mismatches {:?}",
+                index + 1,
+                reflection.axis,
+                reflection.position,
+                reflection.smudges.len(),
+                reflection.smudges
+            );
+        }
+    }
+    Ok(())
+}
+
+fn part_one(patterns: &[Pattern]) -> Result<usize, String> {
+    summarize(patterns, 0)
+}
+
+fn part_two(patterns: &[Pattern]) -> Result<usize, String> {
+    summarize(patterns, 1)
 }
 
-fn parse_pattern(pattern: &str) -> Vec<Vec<u8>> {
-    pattern
-        .lines()
-        .map(|line| line.chars().map(|char| (char == '#') as u8).collect())
-        .collect()
+/// Sums, over all patterns, the single reflection line that needs exactly `smudges` cells fixed.
+/// A pattern with no such line or with several of them is an error, as the score would be
+/// ambiguous; `report` lists every line instead.
+fn summarize(patterns: &[Pattern], smudges: usize) -> Result<usize, String> {
+    patterns
+        .iter()
+        .enumerate()
+        .map(|(index, pattern)| {
+            let reflections = find_reflections(pattern, smudges)
+                .into_iter()
+                .filter(|reflection| reflection.smudges.len() == smudges)
+                .collect::<Vec<_>>();
+            match reflections.as_slice() {
+                [reflection] => Ok(reflection.summary()),
+                [] => Err(format!(
+                    "Pattern {} has no reflection with {smudges} smudges",
+                    index + 1
+                )),
+                _ => Err(format!(
+                    "Pattern {} has {} reflections with {smudges} smudges",
+                    index + 1,
+                    reflections.len()
+                )),
+            }
+        })
+        .sum()
 }
 
-fn summarize_reflections(pattern: Vec<Vec<u8>>) -> usize {
-    let width = pattern[0].len();
-    let height = pattern.len();
+/// A row or column of a pattern as a bitset, so that mirror checks are XOR plus popcount.
+#[derive(Debug, Clone)]
+struct BitLine {
+    words: Vec<u64>,
+}
 
-    for col in 1..width {
-        let left_half = (0..col).rev();
-        let right_half = col..width;
-        if left_half
-            .zip(right_half)
-            .all(|(l, r)| get_col(&pattern, l) == get_col(&pattern, r))
-        {
-            return col;
+impl BitLine {
+    fn new(length: usize) -> BitLine {
+        BitLine {
+            words: vec![0; length.div_ceil(64)],
         }
     }
 
-    for row in 1..height {
-        let top_half = (0..row).rev();
-        let bottom_half = row..height;
-        if top_half
-            .zip(bottom_half)
-            .all(|(t, b)| pattern[t] == pattern[b])
-        {
-            return row * 100;
+    fn set(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    fn mismatches(&self, other: &BitLine) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a ^ b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Indices of the bits that differ between both lines.
+    fn diff_positions<'a>(&'a self, other: &'a BitLine) -> impl Iterator<Item = usize> + 'a {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .enumerate()
+            .flat_map(|(word, (a, b))| {
+                let mut diff = a ^ b;
+                std::iter::from_fn(move || {
+                    if diff == 0 {
+                        return None;
+                    }
+                    let bit = diff.trailing_zeros() as usize;
+                    diff &= diff - 1;
+                    Some(word * 64 + bit)
+                })
+            })
+    }
+}
+
+#[derive(Debug)]
+struct Pattern {
+    rows: Vec<BitLine>,
+    cols: Vec<BitLine>,
+}
+
+fn parse_patterns(input: &str) -> Result<Vec<Pattern>, String> {
+    let blocks = split_grids(input).map_err(|err| err.to_string())?;
+    Ok(blocks.iter().map(parse_pattern).collect())
+}
+
+fn parse_pattern(block: &Block) -> Pattern {
+    let lines = &block.lines;
+    let height = lines.len();
+    let width = lines.first().map_or(0, |line| line.chars().count());
+
+    let mut rows = vec![BitLine::new(width); height];
+    let mut cols = vec![BitLine::new(height); width];
+    for (row, line) in lines.iter().enumerate() {
+        for (col, _) in line.chars().enumerate().filter(|&(_, c)| c == '#') {
+            rows[row].set(col);
+            cols[col].set(row);
         }
     }
 
-    0
+    Pattern { rows, cols }
 }
 
-fn summarize_reflections_with_smudge(pattern: Vec<Vec<u8>>) -> usize {
-    let width = pattern[0].len();
-    let height = pattern.len();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    /// Mirror between columns `position - 1` and `position`.
+    Vertical,
+    /// Mirror between rows `position - 1` and `position`.
+    Horizontal,
+}
 
-    (1..width)
-        .find(|&col| {
-            (0..col)
-                .rev()
-                .zip(col..width)
-                .fold(0, |diffs, (left, right)| {
-                    if diffs > 1 {
-                        return 2;
-                    }
-                    diffs + compare_cols(&pattern, left, right)
-                })
-                == 1
+#[derive(Debug)]
+struct Reflection {
+    axis: Axis,
+    position: usize,
+    /// (row, col) of every cell that differs from its mirror image, taken from the top/left half.
+    smudges: Vec<(usize, usize)>,
+}
+
+impl Reflection {
+    fn summary(&self) -> usize {
+        match self.axis {
+            Axis::Vertical => self.position,
+            Axis::Horizontal => self.position * 100,
+        }
+    }
+}
+
+/// Returns every mirror line, vertical ones first, with at most `tolerance` mismatching cells.
+fn find_reflections(pattern: &Pattern, tolerance: usize) -> Vec<Reflection> {
+    let vertical = (1..pattern.cols.len()).filter_map(|col| {
+        mirror_smudges(&pattern.cols, col, tolerance).map(|smudges| Reflection {
+            axis: Axis::Vertical,
+            position: col,
+            smudges: smudges.into_iter().map(|(col, row)| (row, col)).collect(),
         })
-        .map_or_else( // If no column reflections are found, check rows
-            || {
-                (1..height)
-                    .find_map(|row| {
-                        let diffs =
-                            (0..row)
-                                .rev()
-                                .zip(row..height)
-                                .fold(0, |diffs, (top, bottom)| {
-                                    if diffs > 1 {
-                                        return 2;
-                                    }
-                                    diffs + compare_rows(&pattern, top, bottom)
-                                });
-                        if diffs == 1 {
-                            Some(row * 100)
-                        } else {
-                            None
-                        }
-                    })
-                    .unwrap_or(0)
-            },
-            |col| col,
-        )
-}
-
-fn get_col(pattern: &[Vec<u8>], col: usize) -> Vec<u8> {
-    pattern.iter().map(|row| row[col]).collect()
-}
-
-fn compare_cols(pattern: &[Vec<u8>], t: usize, b: usize) -> usize {
-    pattern
-        .iter()
-        .map(|row| (row[t], row[b]))
-        .filter(|(c1, c2)| c1 != c2)
-        .count()
+    });
+
+    let horizontal = (1..pattern.rows.len()).filter_map(|row| {
+        mirror_smudges(&pattern.rows, row, tolerance).map(|smudges| Reflection {
+            axis: Axis::Horizontal,
+            position: row,
+            smudges,
+        })
+    });
+
+    vertical.chain(horizontal).collect()
 }
 
-fn compare_rows(pattern: &[Vec<u8>], l: usize, r: usize) -> usize {
-    pattern[l]
-        .iter()
-        .zip(&pattern[r])
-        .filter(|(c1, c2)| c1 != c2)
-        .count()
+/// Checks a mirror placed before `lines[position]`. Returns the (line, index) of every
+/// mismatching cell on the near side, or `None` if there are more than `tolerance`.
+fn mirror_smudges(
+    lines: &[BitLine],
+    position: usize,
+    tolerance: usize,
+) -> Option<Vec<(usize, usize)>> {
+    let pairs = (0..position).rev().zip(position..lines.len());
+
+    let mut mismatches = 0;
+    for (near, far) in pairs.clone() {
+        mismatches += lines[near].mismatches(&lines[far]);
+        if mismatches > tolerance {
+            return None;
+        }
+    }
+
+    Some(
+        pairs
+            .flat_map(|(near, far)| {
+                lines[near]
+                    .diff_positions(&lines[far])
+                    .map(move |index| (near, index))
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn several_mirror_lines_are_an_error() {
+        let patterns = parse_patterns("##\n##\n").unwrap();
+        assert_eq!(find_reflections(&patterns[0], 0).len(), 2);
+        assert!(part_one(&patterns).is_err());
+    }
+
+    #[test]
+    fn single_mirror_line_is_scored() {
+        let patterns = parse_patterns("#.#.\n.##.\n.##.\n").unwrap();
+        assert_eq!(part_one(&patterns), Ok(200));
+    }
+
+    #[test]
+    fn non_ascii_cells_are_one_column() {
+        let pattern = &parse_patterns("###\n#é#\n###\n").unwrap()[0];
+        assert_eq!((pattern.rows.len(), pattern.cols.len()), (3, 3));
+        let patterns = parse_patterns("#é#\n#é#\n").unwrap();
+        assert_eq!(part_one(&patterns), Ok(100));
+    }
 }
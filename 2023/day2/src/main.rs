@@ -1,71 +1,229 @@
-use std::cmp::max;
+use std::collections::BTreeMap;
+
+use common::bench::Bencher;
+
+const COLOURS: [&str; 3] = ["red", "green", "blue"];
+
+const DEFAULT_BAG: &str = "12 red, 13 green, 14 blue";
 
 fn main() {
     let input = include_str!("input");
-    part_one(input);
-    part_two(input);
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if let Err(err) = run(input, &args) {
+        eprintln!("{err}");
+        eprintln!("Usage: day2 [--bag <cubes> | --bag-file <file>] [possible | smallest <from> <to> | binding]");
+        std::process::exit(1);
+    }
+}
+
+fn run(input: &str, args: &[String]) -> Result<(), String> {
+    let mut bag = parse_round(DEFAULT_BAG)?;
+    let mut args = args.iter().map(String::as_str).peekable();
+
+    while let Some(&arg) = args.peek() {
+        match arg {
+            "--bag" => {
+                args.next();
+                bag = parse_round(args.next().ok_or("Missing bag")?)?;
+            }
+            "--bag-file" => {
+                args.next();
+                let path = args.next().ok_or("Missing bag file")?;
+                let contents =
+                    std::fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+                bag = parse_round(&contents.lines().collect::<Vec<_>>().join(","))?;
+            }
+            _ => break,
+        }
+    }
+
+    let games = parse_games(input)?;
+
+    match args.next() {
+        None => {
+            println!("Part 1: {:?}", part_one(&games, &bag));
+            println!("Part 2: {}", part_two(&games));
+        }
+        Some("bench") => {
+            let mut bencher = Bencher::new("day2");
+            bencher.bench("parse", || parse_games(input));
+            bencher.bench("part1", || part_one(&games, &bag));
+            bencher.bench("part2", || part_two(&games));
+        }
+        Some("possible") => {
+            let ids = possible_games(&games, &bag)
+                .iter()
+                .map(|g| g.id.to_string())
+                .collect::<Vec<_>>();
+            println!("Possible games: {}", ids.join(", "));
+        }
+        Some("smallest") => {
+            let mut bounds =
+                args.map(|n| n.parse::<u32>().map_err(|_| format!("Invalid game id {n}")));
+            let from = bounds.next().ok_or("Missing first game")??;
+            let to = bounds.next().ok_or("Missing last game")??;
+            println!(
+                "Smallest bag for games {from}..={to}: {}",
+                format_round(&smallest_bag(&games, from..=to))
+            );
+        }
+        Some("binding") => {
+            for game in &games {
+                match game.binding_constraint(&bag) {
+                    Some((colour, slack)) if slack < 0 => {
+                        println!(
+                            "Game {}: impossible, {colour} exceeds the bag by {}",
+                            game.id, -slack
+                        )
+                    }
+                    Some((colour, slack)) => {
+                        println!("Game {}: {colour}, {slack} to spare", game.id)
+                    }
+                    None => println!("Game {}: no cubes revealed", game.id),
+                }
+            }
+        }
+        Some(query) => return Err(format!("Unknown query {query}")),
+    }
+
+    Ok(())
 }
 
-fn part_one(input: &str) {
-   let (red, green, blue) = (12, 13, 14); 
+fn possible_games<'a>(games: &'a [Game], bag: &Round) -> Vec<&'a Game> {
+    games.iter().filter(|game| game.is_valid(bag)).collect()
+}
+
+/// The smallest bag that makes every game with an id in `ids` possible.
+fn smallest_bag(games: &[Game], ids: std::ops::RangeInclusive<u32>) -> Round {
+    games
+        .iter()
+        .filter(|game| ids.contains(&game.id))
+        .flat_map(Game::maxima)
+        .fold(Round::new(), |mut bag, (colour, value)| {
+            let max = bag.entry(colour).or_insert(0);
+            *max = (*max).max(value);
+            bag
+        })
+}
 
-    let result: u32 = input.lines()
-        .map(|line| Game::new(line))
-        .filter(|game| game.is_valid(red, green, blue))
+fn part_one(games: &[Game], bag: &Round) -> u32 {
+    games
+        .iter()
+        .filter(|game| game.is_valid(bag))
         .map(|g| g.id)
-        .sum();
-    println!("Part 1: {:?}", result);
+        .sum()
+}
+
+fn part_two(games: &[Game]) -> u32 {
+    games.iter().map(|g| g.get_power(&COLOURS)).sum()
+}
+
+fn parse_games(input: &str) -> Result<Vec<Game>, String> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(n, line)| Game::new(line).map_err(|err| format!("Line {}: {err}", n + 1)))
+        .collect()
+}
+
+/// Cubes of each colour shown in one reveal, or held in a bag.
+type Round = BTreeMap<String, u32>;
+
+/// Parses a list such as `3 blue, 4 red`. Repeated colours add up.
+fn parse_round(round: &str) -> Result<Round, String> {
+    round
+        .split(',')
+        .map(str::trim)
+        .filter(|cubes| !cubes.is_empty())
+        .map(|cubes| {
+            let (value, colour) = cubes
+                .split_once(' ')
+                .ok_or(format!("Invalid cubes {cubes:?}"))?;
+            let value = value
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid count {value:?}"))?;
+            Ok((colour.trim().to_string(), value))
+        })
+        .try_fold(Round::new(), |mut round, cubes: Result<_, String>| {
+            let (colour, value) = cubes?;
+            *round.entry(colour).or_insert(0) += value;
+            Ok(round)
+        })
 }
 
-fn part_two(input: &str) {
-     let result: u32 = input.lines()
-        .map(|line| Game::new(line))
-        .map(|g| g.get_power())
-        .sum();
-    println!("Part 2: {}", result);
+fn format_round(round: &Round) -> String {
+    round
+        .iter()
+        .map(|(colour, value)| format!("{value} {colour}"))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 #[derive(Debug)]
 pub struct Game {
     id: u32,
-    red: u32,
-    green: u32,
-    blue: u32,
+    rounds: Vec<Round>,
 }
 
 impl Game {
-    
-    fn new(line: &str) -> Game {
-        let mut red = 0;
-        let mut green = 0;
-        let mut blue  = 0;
-
-        let id = line.split(":").collect::<Vec<_>>()[0].split(" ").collect::<Vec<_>>()[1].parse::<u32>().unwrap();
-        let cube_reveals = line.split(":").collect::<Vec<_>>()[1].replace(";", ",");
-
-        for ele in cube_reveals.trim().split(",") {
-           let (value, colour) = ele.trim().split_once(" ").unwrap();
-            match colour {
-                "red" => red = max(red, value.parse::<u32>().unwrap()),
-                "green" => green = max(green, value.parse::<u32>().unwrap()),
-                "blue" => blue = max(blue, value.parse::<u32>().unwrap()),
-                _ => continue
-            }
-        }
+    fn new(line: &str) -> Result<Game, String> {
+        let (header, reveals) = line.split_once(':').ok_or("Missing ':'")?;
+        let id = header
+            .trim()
+            .strip_prefix("Game ")
+            .ok_or(format!("Expected 'Game <id>', found {header:?}"))?;
+        let id = id
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid game id {id:?}"))?;
 
-        return Game {
-            id,
-            red,
-            green,
-            blue
-        }
+        let rounds = reveals
+            .split(';')
+            .map(parse_round)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Game { id, rounds })
+    }
+
+    /// The most cubes of each colour revealed at once, i.e. the smallest bag that allows the game.
+    fn maxima(&self) -> Round {
+        self.rounds
+            .iter()
+            .flatten()
+            .fold(Round::new(), |mut maxima, (colour, &value)| {
+                let max = maxima.entry(colour.clone()).or_insert(0);
+                *max = (*max).max(value);
+                maxima
+            })
+    }
+
+    /// A game is possible if no round shows more cubes of a colour than the bag holds.
+    /// Colours missing from the bag count as zero cubes.
+    fn is_valid(&self, bag: &Round) -> bool {
+        self.maxima()
+            .iter()
+            .all(|(colour, &value)| value <= bag.get(colour).copied().unwrap_or(0))
     }
 
-    fn is_valid(&self, red: u32, green: u32, blue: u32) -> bool {
-        return self.red <= red && self.green <= green && self.blue <= blue;
+    /// The colour with the least room left in `bag`, and how many cubes it has to spare.
+    /// A negative slack means the game is impossible with this bag.
+    fn binding_constraint(&self, bag: &Round) -> Option<(String, i64)> {
+        self.maxima()
+            .into_iter()
+            .map(|(colour, value)| {
+                let limit = bag.get(&colour).copied().unwrap_or(0);
+                let slack = limit as i64 - value as i64;
+                (colour, slack)
+            })
+            .min_by_key(|&(_, slack)| slack)
     }
 
-    fn get_power(&self) -> u32 {
-        return self.red * self.blue * self.green;
+    /// Product of the minimum number of cubes needed of each of `colours`.
+    fn get_power(&self, colours: &[&str]) -> u32 {
+        let maxima = self.maxima();
+        colours
+            .iter()
+            .map(|&colour| maxima.get(colour).copied().unwrap_or(0))
+            .product()
     }
 }
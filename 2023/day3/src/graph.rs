@@ -0,0 +1,140 @@
+use serde::Serialize;
+
+use crate::Schematic;
+
+/// Bipartite graph between symbols and the part numbers they touch. Node indices on one side
+/// are used as edges on the other. Rows and columns are 1-based character positions.
+#[derive(Debug, Serialize)]
+pub struct AdjacencyGraph {
+    pub symbols: Vec<SymbolNode>,
+    pub parts: Vec<PartNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SymbolNode {
+    pub symbol: char,
+    pub row: usize,
+    pub col: usize,
+    pub parts: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PartNode {
+    pub value: u64,
+    pub row: usize,
+    pub col: usize,
+    pub len: usize,
+    pub symbols: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Product,
+    Sum,
+}
+
+/// Which symbols count as gears, how many part numbers they must touch, and how those numbers
+/// are combined into a ratio.
+#[derive(Debug, Clone)]
+pub struct GearRule {
+    pub symbols: Vec<char>,
+    pub neighbours: usize,
+    pub aggregation: Aggregation,
+}
+
+impl Default for GearRule {
+    fn default() -> Self {
+        GearRule {
+            symbols: vec!['*'],
+            neighbours: 2,
+            aggregation: Aggregation::Product,
+        }
+    }
+}
+
+impl AdjacencyGraph {
+    pub fn new(schematic: &Schematic) -> Self {
+        let mut parts = schematic
+            .numbers
+            .iter()
+            .map(|number| PartNode {
+                value: number.value,
+                row: number.start.x as usize + 1,
+                col: number.start.y as usize + 1,
+                len: number.len,
+                symbols: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        let symbols = schematic
+            .symbols
+            .iter()
+            .enumerate()
+            .map(|(index, (symbol, position))| {
+                let adjacent = schematic.adjacent_numbers(position);
+                for &id in &adjacent {
+                    parts[id].symbols.push(index);
+                }
+                SymbolNode {
+                    symbol: *symbol,
+                    row: position.x as usize + 1,
+                    col: position.y as usize + 1,
+                    parts: adjacent,
+                }
+            })
+            .collect();
+
+        // Numbers that touch no symbol are not part numbers.
+        let mut ids = vec![None; parts.len()];
+        let mut next = 0;
+        for (id, part) in parts.iter().enumerate() {
+            if !part.symbols.is_empty() {
+                ids[id] = Some(next);
+                next += 1;
+            }
+        }
+        parts.retain(|part| !part.symbols.is_empty());
+        let mut graph = AdjacencyGraph { symbols, parts };
+        for symbol in &mut graph.symbols {
+            symbol.parts = symbol.parts.iter().filter_map(|&id| ids[id]).collect();
+        }
+
+        graph
+    }
+
+    /// Sum over every (symbol, part) edge, so a number touching two symbols counts twice.
+    pub fn part_sum(&self) -> u64 {
+        self.symbols
+            .iter()
+            .flat_map(|symbol| &symbol.parts)
+            .map(|&id| self.parts[id].value)
+            .sum()
+    }
+
+    /// Symbols that are gears under `rule`, with their ratios.
+    pub fn gears<'a>(
+        &'a self,
+        rule: &'a GearRule,
+    ) -> impl Iterator<Item = (&'a SymbolNode, u64)> + 'a {
+        self.symbols
+            .iter()
+            .filter(|symbol| rule.symbols.contains(&symbol.symbol))
+            .filter(|symbol| symbol.parts.len() == rule.neighbours)
+            .map(|symbol| {
+                let values = symbol.parts.iter().map(|&id| self.parts[id].value);
+                let ratio = match rule.aggregation {
+                    Aggregation::Product => values.product(),
+                    Aggregation::Sum => values.sum(),
+                };
+                (symbol, ratio)
+            })
+    }
+
+    pub fn gear_total(&self, rule: &GearRule) -> u64 {
+        self.gears(rule).map(|(_, ratio)| ratio).sum()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
@@ -1,89 +1,200 @@
-use regex::Regex;
+use common::bench::Bencher;
+use graph::{AdjacencyGraph, Aggregation, GearRule};
+
+mod graph;
 
 struct GridSize {
     rows: i32,
     cols: i32,
 }
 
+/// A cell of the schematic. `y` counts characters, not bytes, so multi-byte symbols such as
+/// '§' or '€' take a single column like any other cell.
+#[derive(Debug, Clone, Copy)]
 struct Position {
     x: i32,
     y: i32,
 }
 
 impl GridSize {
-    fn new(input: &str) -> Self {
-        let cols = input.lines().next().unwrap().chars().count() as i32;
-        let rows = input.lines().count() as i32;
-        GridSize { rows, cols }
-    }
-
     fn is_within_bounds(&self, position: &Position) -> bool {
         position.x >= 0 && position.x < self.rows && position.y >= 0 && position.y < self.cols
     }
+
+    fn index(&self, position: &Position) -> usize {
+        (position.x * self.cols + position.y) as usize
+    }
 }
 
-fn main() {
-    let input = include_str!("input");
-    let grid_size = GridSize::new(input);
-    println!("Part one: {}", part_one(input, &grid_size));
-    println!("Part two: {}", part_two(input, &grid_size));
+#[derive(Debug, Clone, Copy)]
+struct Number {
+    value: u64,
+    start: Position,
+    /// Width in columns.
+    len: usize,
 }
 
-fn part_one(input: &str, grid_size: &GridSize) -> u32 {
-    let numbers = get_numbers(input);
-    let symbols = get_symbols(input);
+/// The schematic after a single scan: every number gets an ID, and every cell records the ID
+/// of the number covering it, so adjacency checks only look at a symbol's 8 neighbours.
+struct Schematic {
+    grid_size: GridSize,
+    numbers: Vec<Number>,
+    cells: Vec<Option<usize>>,
+    symbols: Vec<(char, Position)>,
+}
 
-    let mut parts = Vec::new();
-    for (number, start_position) in &numbers {
-        let end_position = Position {
-            x: start_position.x,
-            y: start_position.y + number.len() as i32 - 1,
-        };
-        for (_, symbol_position) in &symbols {
-            let neighbours = get_neighbours(symbol_position)
-                .into_iter()
-                .filter(|pos| grid_size.is_within_bounds(pos))
-                .collect::<Vec<_>>();
-            for neighbour in &neighbours {
-                if is_in_line_with(neighbour, start_position, &end_position) {
-                    parts.push(number.parse::<u32>().unwrap());
-                    break;
+impl Schematic {
+    fn new(input: &str) -> Self {
+        let rows = input.lines().count() as i32;
+        let cols = input
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0) as i32;
+        let grid_size = GridSize { rows, cols };
+
+        let mut numbers = Vec::new();
+        let mut cells = vec![None; (rows * cols) as usize];
+        let mut symbols = Vec::new();
+
+        for (i, line) in input.lines().enumerate() {
+            let mut current = None;
+            for (j, c) in line.chars().enumerate() {
+                let position = Position {
+                    x: i as i32,
+                    y: j as i32,
+                };
+                match c.to_digit(10) {
+                    Some(digit) => {
+                        let id = *current.get_or_insert_with(|| {
+                            numbers.push(Number {
+                                value: 0,
+                                start: position,
+                                len: 0,
+                            });
+                            numbers.len() - 1
+                        });
+                        numbers[id].value = numbers[id].value * 10 + digit as u64;
+                        numbers[id].len += 1;
+                        cells[grid_size.index(&position)] = Some(id);
+                    }
+                    None => {
+                        current = None;
+                        if c != '.' {
+                            symbols.push((c, position));
+                        }
+                    }
                 }
             }
         }
+
+        Schematic {
+            grid_size,
+            numbers,
+            cells,
+            symbols,
+        }
     }
 
-    parts.iter().sum()
+    /// IDs of the distinct numbers touching `position`.
+    fn adjacent_numbers(&self, position: &Position) -> Vec<usize> {
+        let mut ids = get_neighbours(position)
+            .into_iter()
+            .filter(|pos| self.grid_size.is_within_bounds(pos))
+            .filter_map(|pos| self.cells[self.grid_size.index(&pos)])
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
 }
 
-fn part_two(input: &str, grid_size: &GridSize) -> u32 {
-    let numbers = get_numbers(input);
-    let gears = get_gears(input);
-    let mut ratios = Vec::new();
+fn main() {
+    let input = include_str!("input");
+    let schematic = Schematic::new(input);
+    let graph = AdjacencyGraph::new(&schematic);
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
 
-    for (_, gear_position) in &gears {
-        let neighbours = get_neighbours(gear_position)
-            .into_iter()
-            .filter(|pos| grid_size.is_within_bounds(pos))
-            .collect::<Vec<_>>();
-        let mut adjacent_numbers = Vec::new();
-        for (number, start_position) in &numbers {
-            let end_position = Position {
-                x: start_position.x,
-                y: start_position.y + number.len() as i32 - 1,
-            };
-            for neighbour in &neighbours {
-                if is_in_line_with(neighbour, start_position, &end_position) {
-                    adjacent_numbers.push(number.parse::<u32>().unwrap());
-                    break;
+    match args.first().map(String::as_str) {
+        Some("parts") => print_parts(&schematic),
+        Some("bench") => {
+            let mut bencher = Bencher::new("day3");
+            bencher.bench("parse", || AdjacencyGraph::new(&Schematic::new(input)));
+            bencher.bench("part1", || part_one(&graph));
+            bencher.bench("part2", || part_two(&graph));
+        }
+        Some("graph") => println!("{}", graph.to_json()),
+        Some("gears") => {
+            let rule = match parse_rule(&args[1..]) {
+                Ok(rule) => rule,
+                Err(err) => {
+                    eprintln!("{err}");
+                    eprintln!("Usage: day3 gears [--symbols <chars>] [--neighbours <n>] [--sum]");
+                    std::process::exit(1);
                 }
+            };
+            for (symbol, ratio) in graph.gears(&rule) {
+                println!(
+                    "'{}' at {}:{} -> {}",
+                    symbol.symbol, symbol.row, symbol.col, ratio
+                );
             }
+            println!("Total: {}", graph.gear_total(&rule));
         }
-        if adjacent_numbers.len() == 2 {
-            ratios.push(adjacent_numbers.iter().product());
+        _ => {
+            println!("Part one: {}", part_one(&graph));
+            println!("Part two: {}", part_two(&graph));
+        }
+    }
+}
+
+/// Parses `[--symbols <chars>] [--neighbours <n>] [--sum]` on top of the default gear rule.
+fn parse_rule(args: &[String]) -> Result<GearRule, String> {
+    let mut rule = GearRule::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--symbols" => rule.symbols = args.next().ok_or("Missing symbols")?.chars().collect(),
+            "--neighbours" => {
+                let neighbours = args.next().ok_or("Missing neighbour count")?;
+                rule.neighbours = neighbours
+                    .parse()
+                    .map_err(|_| format!("Invalid count {neighbours}"))?;
+            }
+            "--sum" => rule.aggregation = Aggregation::Sum,
+            _ => return Err(format!("Unknown argument {arg}")),
+        }
+    }
+
+    Ok(rule)
+}
+
+fn part_one(graph: &AdjacencyGraph) -> u64 {
+    graph.part_sum()
+}
+
+fn part_two(graph: &AdjacencyGraph) -> u64 {
+    graph.gear_total(&GearRule::default())
+}
+
+/// Lists every part number with its row and column span, next to the symbols it touches.
+fn print_parts(schematic: &Schematic) {
+    for (symbol, position) in &schematic.symbols {
+        for id in schematic.adjacent_numbers(position) {
+            let number = &schematic.numbers[id];
+            println!(
+                "{} at {}:{}-{} touches '{}' at {}:{}",
+                number.value,
+                number.start.x + 1,
+                number.start.y + 1,
+                number.start.y + number.len as i32,
+                symbol,
+                position.x + 1,
+                position.y + 1
+            );
         }
     }
-    ratios.iter().sum()
 }
 
 fn get_neighbours(position: &Position) -> Vec<Position> {
@@ -123,59 +234,41 @@ fn get_neighbours(position: &Position) -> Vec<Position> {
     ]
 }
 
-fn is_in_line_with(position: &Position, start: &Position, end: &Position) -> bool {
-    position.x == start.x && position.y >= start.y && position.y <= end.y
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn get_numbers(input: &str) -> Vec<(&str, Position)> {
-    let mut numbers = Vec::new();
-    let num_regex = Regex::new(r"\d+").unwrap();
-    for (i, line) in input.lines().enumerate() {
-        for regex_match in num_regex.find_iter(line) {
-            numbers.push((
-                regex_match.as_str(),
-                Position {
-                    x: i as i32,
-                    y: regex_match.start() as i32,
-                },
-            ));
-        }
+    const UNICODE: &str = include_str!("demoinput_unicode");
+
+    #[test]
+    fn unicode_symbols_match_ascii_answer() {
+        let graph = AdjacencyGraph::new(&Schematic::new(UNICODE));
+        assert_eq!(part_one(&graph), 4361);
     }
-    numbers
-}
 
-fn get_symbols(input: &str) -> Vec<(char, Position)> {
-    let mut symbols = Vec::new();
-    for (i, line) in input.lines().enumerate() {
-        for (j, c) in line.chars().enumerate() {
-            if !c.is_ascii_digit() && c != '.' {
-                symbols.push((
-                    c,
-                    Position {
-                        x: i as i32,
-                        y: j as i32,
-                    },
-                ));
-            }
-        }
+    #[test]
+    fn columns_count_chars_after_multibyte_symbols() {
+        let schematic = Schematic::new("€€.12§\n");
+        let number = schematic.numbers[0];
+        assert_eq!((number.value, number.start.y, number.len), (12, 3, 2));
+        assert_eq!(schematic.adjacent_numbers(&schematic.symbols[2].1), vec![0]);
     }
-    symbols
-}
 
-fn get_gears(input: &str) -> Vec<(char, Position)> {
-    let mut gears = Vec::new();
-    for (i, line) in input.lines().enumerate() {
-        for (j, c) in line.chars().enumerate() {
-            if c == '*' {
-                gears.push((
-                    c,
-                    Position {
-                        x: i as i32,
-                        y: j as i32,
-                    },
-                ));
-            }
-        }
+    #[test]
+    fn numbers_touch_unicode_symbols() {
+        let schematic = Schematic::new(UNICODE);
+        let touching = |symbol: usize| {
+            let (c, position) = &schematic.symbols[symbol];
+            let values = schematic
+                .adjacent_numbers(position)
+                .into_iter()
+                .map(|id| schematic.numbers[id].value)
+                .collect::<Vec<_>>();
+            (*c, values)
+        };
+
+        assert_eq!(touching(0), ('€', vec![467, 35]));
+        assert_eq!(touching(1), ('§', vec![633]));
+        assert_eq!(touching(2), ('€', vec![617]));
     }
-    gears
 }
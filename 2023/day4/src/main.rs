@@ -1,63 +1,178 @@
+use std::collections::HashSet;
+
+use common::bench::Bencher;
+
 fn main() {
     let input = include_str!("input");
-    println!("Part one: {}", part_one(input));
-    println!("Part two: {}", part_two(input));
-}   
-
-fn part_one(input: &str) -> u32 {
-    input.lines()
-        .filter_map(|l| l.split_once(":"))
-        .map(|(_, numbers)| {
-            let (winning_numbers, numbers) = numbers.split_once("|").unwrap();
-            let num_set: std::collections::HashSet<_> = numbers.trim().split_whitespace().collect();
-            let common = winning_numbers.trim().split_whitespace()
-                .filter(|n| num_set.contains(n))
-                .collect::<Vec<_>>();
-            let mut points = 0;
-            for _ in common {
-                if points == 0 {
-                    points = 1;
-                } else {
-                    points *= 2;
-                }
-            }
+    let cards = parse_cards(input).unwrap();
+
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        let mut bencher = Bencher::new("day4");
+        bencher.bench("parse", || parse_cards(input));
+        bencher.bench("part1", || part_one(&cards));
+        bencher.bench("part2", || part_two(&cards));
+        return;
+    }
+
+    if std::env::args().nth(1).as_deref() == Some("trace") {
+        print_cascade(&cascade(&cards).unwrap());
+        return;
+    }
+
+    println!("Part one: {}", part_one(&cards));
+    println!("Part two: {}", part_two(&cards).unwrap());
+}
+
+#[derive(Debug)]
+struct Card {
+    id: u32,
+    winning: HashSet<u32>,
+    have: Vec<u32>,
+    matches: usize,
+}
+
+impl Card {
+    /// Parses a line like `Card   1: 41 48 83 | 83 86  6 31`.
+    fn parse(line: &str) -> Result<Card, String> {
+        let (header, numbers) = line.split_once(':').ok_or("Missing ':'")?;
+        let id = header
+            .strip_prefix("Card")
+            .ok_or("Missing 'Card' prefix")?
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid card id in {header:?}"))?;
+        let (winning, have) = numbers.split_once('|').ok_or("Missing '|'")?;
+        let winning = parse_numbers(winning)?.into_iter().collect::<HashSet<_>>();
+        let have = parse_numbers(have)?;
+
+        let mut card = Card {
+            id,
+            winning,
+            have,
+            matches: 0,
+        };
+        card.matches = card.count_matches();
+        Ok(card)
+    }
+
+    fn count_matches(&self) -> usize {
+        self.have
+            .iter()
+            .filter(|n| self.winning.contains(n))
+            .count()
+    }
+
+    fn points(&self) -> u32 {
+        match self.matches {
+            0 => 0,
+            matches => 1 << (matches - 1),
+        }
+    }
+}
 
-            Some(points)
+fn parse_numbers(numbers: &str) -> Result<Vec<u32>, String> {
+    numbers
+        .split_whitespace()
+        .map(|n| {
+            n.parse::<u32>()
+                .map_err(|_| format!("Invalid number {n:?}"))
         })
-        .sum::<Option<u32>>()
-        .unwrap_or_else(|| {
-            println!("No winning numbers");
-            0
+        .collect()
+}
+
+/// Parses every card, checking that they are numbered 1, 2, 3... in order, since copies are
+/// handed out by position.
+fn parse_cards(input: &str) -> Result<Vec<Card>, String> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(n, line)| {
+            let card = Card::parse(line).map_err(|err| format!("Line {}: {err}", n + 1))?;
+            if card.id as usize != n + 1 {
+                return Err(format!(
+                    "Line {}: expected card {}, found card {}",
+                    n + 1,
+                    n + 1,
+                    card.id
+                ));
+            }
+            Ok(card)
         })
+        .collect()
 }
 
-fn part_two(input: &str) -> u32 {
-    let mut count = 0;
-    let mut card_count = vec![1u32];
-    let mut matches: usize;
+fn part_one(cards: &[Card]) -> u32 {
+    cards.iter().map(Card::points).sum()
+}
 
-    for (n, line) in input.lines().enumerate() {
-        let mut words = line.split_whitespace().skip(2);
-        let mut winning = Vec::new();
-        while let Some(x) = words.next() {
-            if x == "|" {
-                break;
-            }
-            winning.push(x);
-        }
-        matches = words.filter(|x| winning.contains(x)).count();
-        let end = n +  matches + 1;
+fn part_two(cards: &[Card]) -> Result<u32, String> {
+    Ok(cascade(cards)?.iter().map(|report| report.copies).sum())
+}
 
-        if end > card_count.len() {
-            card_count.resize(end, 1);
-        }
+#[derive(Debug)]
+struct CardReport {
+    id: u32,
+    matches: usize,
+    /// Instances held at the end, including the original card.
+    copies: u32,
+    /// (card id, copies won from it) for every earlier card that won copies of this one.
+    contributors: Vec<(u32, u32)>,
+}
 
-        for i in n + 1..end {
-            card_count[i] += card_count[n];
+/// Plays out the copy cascade, failing if a card wins copies of cards past the end of the table.
+fn cascade(cards: &[Card]) -> Result<Vec<CardReport>, String> {
+    let mut reports = cards
+        .iter()
+        .map(|card| CardReport {
+            id: card.id,
+            matches: card.matches,
+            copies: 1,
+            contributors: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+
+    for (n, card) in cards.iter().enumerate() {
+        let end = n + card.matches + 1;
+        if end > cards.len() {
+            return Err(format!(
+                "Card {} wins copies up to card {}, but the last card is {}",
+                card.id,
+                card.id as usize + card.matches,
+                cards.len()
+            ));
         }
 
-        count += card_count[n];
+        let copies = reports[n].copies;
+        for report in &mut reports[n + 1..end] {
+            report.copies += copies;
+            report.contributors.push((card.id, copies));
+        }
     }
 
-    count
+    Ok(reports)
+}
+
+fn print_cascade(reports: &[CardReport]) {
+    for report in reports {
+        let contributors = report
+            .contributors
+            .iter()
+            .map(|(id, copies)| format!("{copies} from card {id}"))
+            .collect::<Vec<_>>();
+        println!(
+            "Card {}: {} matches, {} instances{}",
+            report.id,
+            report.matches,
+            report.copies,
+            if contributors.is_empty() {
+                String::new()
+            } else {
+                format!(" (original + {})", contributors.join(", "))
+            }
+        );
+    }
+    println!(
+        "Total: {}",
+        reports.iter().map(|report| report.copies).sum::<u32>()
+    );
 }
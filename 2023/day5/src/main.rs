@@ -1,62 +1,383 @@
-const SECTIONS: usize = 7;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use common::bench::Bencher;
+use common::blocks::{split_blocks, Block};
 
 fn main() {
     let input = include_str!("input");
-    println!("Part one: {}", part_one(input));
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if let Err(err) = run(input, &args) {
+        eprintln!("{err}");
+        eprintln!("Usage: day5 [<source> [<target>] | trace <start> [<end>] | bench]");
+        std::process::exit(1);
+    }
 }
 
-fn part_one(input: &str) -> u64 {
-    let mut lines = input.lines().skip(2);
-    let maps: Vec<Vec<Mapping>> = (0..SECTIONS).map(|_| process_section(&mut lines)).collect();
+fn run(input: &str, args: &[String]) -> Result<(), String> {
+    let almanac = parse_almanac(input)?;
 
-    let seeds: Vec<u64> = input
-        .lines()
-        .next()
-        .unwrap()
-        .split(':')
-        .nth(1)
-        .unwrap()
-        .split_whitespace()
-        .map(|s| s.parse().unwrap())
-        .collect();
-
-    seeds
-        .into_iter()
-        .map(|seed| {
-            maps.iter().fold(seed, |seed, map| {
-                map.iter()
-                    .find(|Mapping { range, .. }| range.contains(&seed))
-                    .map(|Mapping { range, value }| value + seed - range.start)
-                    .unwrap_or(seed)
-            })
+    if args.first().map(String::as_str) == Some("bench") {
+        let mut bencher = Bencher::new("day5");
+        bencher.bench("parse", || parse_almanac(input));
+        bencher.bench("part1", || part_one(&almanac, "seed", "location"));
+        bencher.bench("part2", || part_two(&almanac, "seed", "location"));
+        bencher.bench("part2-reverse", || {
+            part_two_reverse(&almanac, "seed", "location")
+        });
+        return Ok(());
+    }
+
+    if args.first().map(String::as_str) == Some("trace") {
+        let parse = |n: &String| {
+            n.parse::<u64>()
+                .map_err(|_| format!("Invalid location {n}"))
+        };
+        let start = args.get(1).map_or(Ok(0), parse)?;
+        let end = args.get(2).map_or(Ok(start + 1), parse)?;
+        return print_trace(&almanac, start..end);
+    }
+
+    let source = args.first().map_or("seed", String::as_str);
+    let target = args.get(1).map_or("location", String::as_str);
+    println!("Part one: {}", part_one(&almanac, source, target)?);
+    println!("Part two: {}", part_two(&almanac, source, target)?);
+    println!(
+        "Part two (reverse): {}",
+        part_two_reverse(&almanac, source, target)?
+    );
+    Ok(())
+}
+
+/// Lowest `target` value among the seeds, converted from the `source` category.
+fn part_one(almanac: &Almanac, source: &str, target: &str) -> Result<u64, String> {
+    let chain = almanac.chain(source, target)?;
+
+    almanac
+        .seeds
+        .iter()
+        .map(|&seed| chain.iter().fold(seed, |value, map| map.convert(value)))
+        .min()
+        .ok_or("No seeds".to_string())
+}
+
+/// Lowest `target` value for the seed ranges, pushing whole ranges forward through every layer
+/// from the `source` category.
+fn part_two(almanac: &Almanac, source: &str, target: &str) -> Result<u64, String> {
+    let chain = almanac.chain(source, target)?;
+
+    chain
+        .iter()
+        .fold(almanac.seed_ranges(), |ranges, map| {
+            ranges
+                .into_iter()
+                .flat_map(|range| map.convert_range(range))
+                .collect()
         })
+        .iter()
+        .map(|range| range.start)
         .min()
-        .unwrap()
+        .ok_or("No seeds".to_string())
+}
+
+/// Lowest `target` value for the seed ranges, searching upward from 0 and mapping each range
+/// back to the `source` values that produce it.
+fn part_two_reverse(almanac: &Almanac, source: &str, target: &str) -> Result<u64, String> {
+    let chain = almanac.chain(source, target)?;
+    lowest_reachable(&chain, &almanac.seed_ranges(), 0..u64::MAX).ok_or("No seeds".to_string())
+}
+
+/// Lowest value in `range`, at the end of `chain`, that some seed in `seeds` maps to.
+fn lowest_reachable(
+    chain: &[&CategoryMap],
+    seeds: &[Range<u64>],
+    range: Range<u64>,
+) -> Option<u64> {
+    let Some((map, rest)) = chain.split_last() else {
+        return seeds
+            .iter()
+            .map(|seeds| intersect(seeds, &range))
+            .filter(|range| !range.is_empty())
+            .map(|range| range.start)
+            .min();
+    };
+
+    let mut pieces = map.invert_range(range);
+    pieces.sort_by_key(|(_, target)| target.start);
+
+    let mut lowest: Option<u64> = None;
+    for (source, target) in pieces {
+        if lowest.is_some_and(|lowest| lowest <= target.start) {
+            break;
+        }
+        if let Some(value) = lowest_reachable(rest, seeds, source.clone()) {
+            let value = target.start + (value - source.start);
+            lowest = Some(lowest.map_or(value, |lowest| lowest.min(value)));
+        }
+    }
+    lowest
+}
+
+/// Prints every way to reach the `range` of locations from the seed category, with the range
+/// of values at each stage and the seeds of both parts that fall inside it.
+fn print_trace(almanac: &Almanac, range: Range<u64>) -> Result<(), String> {
+    let chain = almanac.chain("seed", "location")?;
+    let mut categories = vec!["seed"];
+    categories.extend(chain.iter().map(|map| map.target.as_str()));
+
+    let seed_ranges = almanac.seed_ranges();
+    for path in trace_back(&chain, range) {
+        let stages = categories
+            .iter()
+            .zip(&path)
+            .map(|(category, range)| format!("{category} {}..{}", range.start, range.end))
+            .collect::<Vec<_>>();
+        println!("{}", stages.join(" -> "));
+
+        let seeds = almanac
+            .seeds
+            .iter()
+            .filter(|seed| path[0].contains(seed))
+            .collect::<Vec<_>>();
+        let ranges = seed_ranges
+            .iter()
+            .map(|seeds| intersect(seeds, &path[0]))
+            .filter(|range| !range.is_empty())
+            .collect::<Vec<_>>();
+        println!("  part one seeds: {seeds:?}, part two seeds: {ranges:?}");
+    }
+    Ok(())
+}
+
+/// Maps `range` back through every layer of `chain`. Each path holds the matching range at
+/// every stage, from the first category to the last.
+fn trace_back(chain: &[&CategoryMap], range: Range<u64>) -> Vec<Vec<Range<u64>>> {
+    let mut paths = vec![vec![range]];
+
+    for map in chain.iter().rev() {
+        paths = paths
+            .into_iter()
+            .flat_map(|path| {
+                map.invert_range(path[0].clone())
+                    .into_iter()
+                    .map(move |(source, target)| {
+                        // Every later stage is the first one shifted, so narrow them alike.
+                        let mut narrowed = vec![source];
+                        narrowed.extend(path.iter().map(|stage| {
+                            let start = stage.start + (target.start - path[0].start);
+                            start..start + (target.end - target.start)
+                        }));
+                        narrowed
+                    })
+            })
+            .collect();
+    }
+
+    paths
+}
+
+fn intersect(a: &Range<u64>, b: &Range<u64>) -> Range<u64> {
+    a.start.max(b.start)..a.end.min(b.end).max(a.start.max(b.start))
+}
+
+struct Almanac {
+    seeds: Vec<u64>,
+    /// Maps indexed by their source category.
+    maps: HashMap<String, CategoryMap>,
+}
+
+impl Almanac {
+    /// The seeds line read as `start length` pairs.
+    fn seed_ranges(&self) -> Vec<Range<u64>> {
+        self.seeds
+            .chunks_exact(2)
+            .map(|pair| pair[0]..pair[0] + pair[1])
+            .collect()
+    }
+
+    /// Follows the maps from `source` until reaching `target`.
+    fn chain(&self, source: &str, target: &str) -> Result<Vec<&CategoryMap>, String> {
+        let mut chain = Vec::new();
+        let mut visited = HashSet::new();
+        let mut category = source;
+
+        while category != target {
+            if !visited.insert(category) {
+                return Err(format!(
+                    "Cycle through '{category}' while looking for '{target}'"
+                ));
+            }
+            let map = self
+                .maps
+                .get(category)
+                .ok_or(format!("No map from '{category}' on the way to '{target}'"))?;
+            chain.push(map);
+            category = &map.target;
+        }
+
+        Ok(chain)
+    }
+}
+
+struct CategoryMap {
+    target: String,
+    mappings: Vec<Mapping>,
+}
+
+impl CategoryMap {
+    fn convert(&self, value: u64) -> u64 {
+        self.mappings
+            .iter()
+            .find(|Mapping { range, .. }| range.contains(&value))
+            .map(
+                |Mapping {
+                     range,
+                     value: start,
+                 }| start + value - range.start,
+            )
+            .unwrap_or(value)
+    }
+
+    /// Image of `range`, split into the pieces that each mapping (or no mapping) applies to.
+    fn convert_range(&self, range: Range<u64>) -> Vec<Range<u64>> {
+        let mut result = Vec::new();
+        for mapping in &self.mappings {
+            let piece = intersect(&mapping.range, &range);
+            if !piece.is_empty() {
+                result.push(
+                    mapping.value + (piece.start - mapping.range.start)
+                        ..mapping.value + (piece.end - mapping.range.start),
+                );
+            }
+        }
+        result.extend(self.unmapped(range));
+        result
+    }
+
+    /// Preimage of `range`: every `(source, target)` pair where the values in `source` are
+    /// sent to `target`. Several sources may land on the same target.
+    fn invert_range(&self, range: Range<u64>) -> Vec<(Range<u64>, Range<u64>)> {
+        let mut result = Vec::new();
+        for mapping in &self.mappings {
+            let destination =
+                mapping.value..mapping.value + (mapping.range.end - mapping.range.start);
+            let target = intersect(&destination, &range);
+            if !target.is_empty() {
+                let start = mapping.range.start + (target.start - mapping.value);
+                let source = start..start + (target.end - target.start);
+                result.push((source, target));
+            }
+        }
+        result.extend(
+            self.unmapped(range)
+                .into_iter()
+                .map(|piece| (piece.clone(), piece)),
+        );
+        result
+    }
+
+    /// Parts of `range` not covered by any source range, which map to themselves.
+    fn unmapped(&self, range: Range<u64>) -> Vec<Range<u64>> {
+        let mut sources = self
+            .mappings
+            .iter()
+            .map(|mapping| mapping.range.clone())
+            .collect::<Vec<_>>();
+        sources.sort_by_key(|source| source.start);
+
+        let mut result = Vec::new();
+        let mut start = range.start;
+        for source in sources {
+            if source.start > start {
+                let gap = start..source.start.min(range.end);
+                if !gap.is_empty() {
+                    result.push(gap);
+                }
+            }
+            start = start.max(source.end);
+            if start >= range.end {
+                return result;
+            }
+        }
+        if start < range.end {
+            result.push(start..range.end);
+        }
+        result
+    }
 }
 
 struct Mapping {
-    range: std::ops::Range<u64>,
+    range: Range<u64>,
     value: u64,
 }
 
-fn parse_line(line: &str) -> Mapping {
-    let mut parts = line.splitn(3, ' ').map(|n| n.parse::<u64>().unwrap());
-    let value = parts.next().unwrap();
-    let start = parts.next().unwrap();
-    let length = parts.next().unwrap();
-    Mapping {
+fn parse_almanac(input: &str) -> Result<Almanac, String> {
+    let blocks = split_blocks(input);
+    let (seeds, sections) = blocks.split_first().ok_or("Empty almanac")?;
+
+    let seeds = seeds.lines[0]
+        .strip_prefix("seeds:")
+        .ok_or("Missing seeds")?
+        .split_whitespace()
+        .map(|s| s.parse().map_err(|_| format!("Invalid seed {s:?}")))
+        .collect::<Result<Vec<u64>, String>>()?;
+
+    let mut maps = HashMap::new();
+    for block in sections {
+        let (source, map) = process_section(block)?;
+        if maps.insert(source.to_string(), map).is_some() {
+            return Err(format!(
+                "Line {}: second map from '{source}'",
+                block.start_line
+            ));
+        }
+    }
+
+    Ok(Almanac { seeds, maps })
+}
+
+fn parse_line(line: &str) -> Result<Mapping, String> {
+    let parts = line
+        .split_whitespace()
+        .map(|n| {
+            n.parse::<u64>()
+                .map_err(|_| format!("Invalid number {n:?}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let [value, start, length] = parts[..] else {
+        return Err(format!("Expected 3 numbers in {line:?}"));
+    };
+    Ok(Mapping {
         range: start..start + length,
         value,
-    }
+    })
 }
 
-fn process_section<'a, I>(lines: &mut I) -> Vec<Mapping>
-where
-    I: Iterator<Item = &'a str>,
-{
-    lines
-        .skip(1)
-        .take_while(|line| !line.is_empty())
-        .map(parse_line)
-        .collect()
+/// Parses a `<source>-to-<target> map:` block.
+fn process_section<'a>(block: &Block<'a>) -> Result<(&'a str, CategoryMap), String> {
+    let header = block.lines[0];
+    let (source, target) = header
+        .strip_suffix(" map:")
+        .and_then(|name| name.split_once("-to-"))
+        .ok_or(format!(
+            "Line {}: invalid header {header:?}",
+            block.start_line
+        ))?;
+
+    let mappings = block.lines[1..]
+        .iter()
+        .enumerate()
+        .map(|(n, line)| {
+            parse_line(line).map_err(|err| format!("Line {}: {err}", block.start_line + n + 1))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((
+        source,
+        CategoryMap {
+            target: target.to_string(),
+            mappings,
+        },
+    ))
 }
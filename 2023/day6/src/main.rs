@@ -1,3 +1,5 @@
+use common::bench::Bencher;
+
 const VELOCITY: i64 = 1;
 
 #[derive(Debug)]
@@ -6,87 +8,436 @@ struct Race {
     distance: i64,
 }
 
+/// One stretch of holding the button: each millisecond adds `acceleration` to the speed.
+/// A phase without a duration lasts for as long as the button is held.
+#[derive(Debug, Clone, Copy)]
+struct Phase {
+    duration: Option<i64>,
+    acceleration: i64,
+}
+
+/// How holding the button charges a boat. The boat waits `start_delay` ms after release
+/// before moving at the charged speed, capped at `max_speed`.
+#[derive(Debug, Clone)]
+struct Boat {
+    phases: Vec<Phase>,
+    max_speed: Option<i64>,
+    start_delay: i64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Wins {
+    count: i64,
+    best_hold: i64,
+    best_distance: i128,
+}
+
+impl Boat {
+    /// The puzzle's boat: every millisecond of holding adds `VELOCITY`, with no cap or delay.
+    fn classic() -> Boat {
+        Boat {
+            phases: vec![Phase {
+                duration: None,
+                acceleration: VELOCITY,
+            }],
+            max_speed: None,
+            start_delay: 0,
+        }
+    }
+
+    fn speed(&self, hold: i64) -> i128 {
+        let mut speed = 0;
+        let mut remaining = hold as i128;
+        for phase in &self.phases {
+            let held = phase
+                .duration
+                .map_or(remaining, |duration| remaining.min(duration as i128));
+            speed += held * phase.acceleration as i128;
+            remaining -= held;
+        }
+        self.max_speed.map_or(speed, |max| speed.min(max as i128))
+    }
+
+    fn distance(&self, hold: i64, time: i64) -> i128 {
+        let moving = (time - hold - self.start_delay).max(0) as i128;
+        self.speed(hold) * moving
+    }
+
+    /// Hold times where the distance formula changes shape: phase boundaries, the moment the
+    /// speed cap is reached and the last hold that still leaves time to move. Between two
+    /// consecutive breakpoints the distance is concave in the hold time.
+    fn breakpoints(&self, time: i64) -> Vec<i64> {
+        let mut points = vec![0, time, (time - self.start_delay).clamp(0, time)];
+
+        let mut elapsed = 0i64;
+        for phase in &self.phases {
+            match phase.duration {
+                Some(duration) => {
+                    elapsed = elapsed.saturating_add(duration);
+                    points.push(elapsed.min(time));
+                }
+                None => break,
+            }
+        }
+
+        if let Some(max) = self.max_speed {
+            let capped = partition_point(0, time, |hold| self.speed(hold) < max as i128);
+            points.push(capped);
+        }
+
+        points.sort_unstable();
+        points.dedup();
+        points
+    }
+
+    /// Counts the hold times that beat the record and finds the best one, without scanning
+    /// every hold time.
+    fn wins(&self, race: &Race) -> Wins {
+        let record = race.distance as i128;
+        let mut wins = Wins {
+            count: 0,
+            best_hold: 0,
+            best_distance: self.distance(0, race.time),
+        };
+
+        let points = self.breakpoints(race.time);
+        for (i, window) in points.windows(2).enumerate() {
+            // Segments share their end points; only the first one counts `lo`.
+            let lo = if i == 0 { window[0] } else { window[0] + 1 };
+            let hi = window[1];
+            if lo > hi {
+                continue;
+            }
+
+            let distance = |hold| self.distance(hold, race.time);
+            let peak = partition_point(lo, hi, |hold| distance(hold + 1) > distance(hold));
+            if distance(peak) > wins.best_distance {
+                wins.best_hold = peak;
+                wins.best_distance = distance(peak);
+            }
+            if distance(peak) > record {
+                let first = partition_point(lo, peak, |hold| distance(hold) <= record);
+                let last = partition_point(peak, hi + 1, |hold| distance(hold) > record) - 1;
+                wins.count += last - first + 1;
+            }
+        }
+
+        wins
+    }
+}
+
+/// First value in `lo..hi` for which `pred` is false, or `hi`, assuming `pred` holds for a
+/// prefix of the range.
+fn partition_point<F: Fn(i64) -> bool>(mut lo: i64, mut hi: i64, pred: F) -> i64 {
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
 fn main() {
     let input = include_str!("input");
-    println!("Part one: {}", part_one(input));
-    println!("Part two: {}", part_two(input));
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if let Err(err) = run(input, &args) {
+        eprintln!("{err}");
+        eprintln!(
+            "Usage: day6 [bench] [--phase <ms|*>:<acceleration>]... [--max-speed <n>] [--delay <ms>]"
+        );
+        std::process::exit(1);
+    }
+}
+
+fn run(input: &str, args: &[String]) -> Result<(), String> {
+    if args.first().map(String::as_str) == Some("bench") {
+        let boat = parse_boat(&args[1..])?;
+        let (races, race) = parse_sheets(input)?;
+        let mut bencher = Bencher::new("day6");
+        bencher.bench("parse", || parse_sheets(input));
+        bencher.bench("part1", || part_one(&races, &boat));
+        bencher.bench("part2", || part_two(&race, &boat));
+        return Ok(());
+    }
+
+    let boat = parse_boat(args)?;
+    let (races, race) = parse_sheets(input)?;
+    print_races(&races, &boat);
+    println!("Part one: {}", part_one(&races, &boat));
+    println!("Part two: {}", part_two(&race, &boat));
+    Ok(())
 }
 
-fn part_one(input: &str) -> i64 {
-    let (times_line, distances_line) = get_lines(input);
+/// Parses `[--phase <ms|*>:<acceleration>]... [--max-speed <n>] [--delay <ms>]`. Without any
+/// phase the classic boat is used.
+fn parse_boat(args: &[String]) -> Result<Boat, String> {
+    let mut boat = Boat::classic();
+    let mut phases = Vec::new();
+    let mut args = args.iter();
 
-    let times: Vec<i64> = times_line
-        .split_whitespace()
-        .skip(1)
-        .map(|x| x.parse::<i64>().unwrap())
-        .collect();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+        match arg.as_str() {
+            "--phase" => {
+                let phase = value()?;
+                let (duration, acceleration) = phase
+                    .split_once(':')
+                    .ok_or(format!("Invalid phase {phase}"))?;
+                let duration = match duration {
+                    "*" => None,
+                    duration => Some(
+                        duration
+                            .parse::<i64>()
+                            .ok()
+                            .filter(|&duration| duration >= 1)
+                            .ok_or(format!(
+                                "Invalid duration {duration}, expected at least 1 ms"
+                            ))?,
+                    ),
+                };
+                let acceleration = acceleration
+                    .parse::<i64>()
+                    .map_err(|_| format!("Invalid acceleration {acceleration}"))?;
+                if acceleration < 0 {
+                    return Err("Acceleration cannot be negative".to_string());
+                }
+                phases.push(Phase {
+                    duration,
+                    acceleration,
+                });
+            }
+            "--max-speed" => {
+                let max = value()?;
+                let max = max
+                    .parse::<i64>()
+                    .map_err(|_| format!("Invalid speed {max}"))?;
+                if max < 0 {
+                    return Err("Maximum speed cannot be negative".to_string());
+                }
+                boat.max_speed = Some(max);
+            }
+            "--delay" => {
+                let delay = value()?;
+                boat.start_delay = delay
+                    .parse()
+                    .map_err(|_| format!("Invalid delay {delay}"))?;
+                if boat.start_delay < 0 {
+                    return Err("Delay cannot be negative".to_string());
+                }
+            }
+            _ => return Err(format!("Unknown argument {arg}")),
+        }
+    }
 
-    let distances: Vec<i64> = distances_line
-        .split_whitespace()
-        .skip(1)
-        .map(|x| x.parse().unwrap())
-        .collect();
+    if !phases.is_empty() {
+        boat.phases = phases;
+    }
+    Ok(boat)
+}
 
-    let races: Vec<Race> = times
-        .iter()
-        .zip(distances.iter())
-        .map(|(&time, &distance)| Race { time, distance })
-        .collect();
+/// The sheet read both ways: the separate races of part one and the single kerned race.
+fn parse_sheets(input: &str) -> Result<(RaceSheet, RaceSheet), String> {
+    Ok((
+        RaceSheet::parse(input, SheetMode::Separate)?,
+        RaceSheet::parse(input, SheetMode::Kerned)?,
+    ))
+}
 
+fn part_one(sheet: &RaceSheet, boat: &Boat) -> i64 {
     let mut total = 1;
 
-    for race in races {
-        total *= calculate_possible_wins(race);
+    for race in &sheet.races {
+        total *= boat.wins(race).count;
     }
 
     total
 }
 
-fn part_two(input: &str) -> i64 {
-    let (times_line, distances_line) = get_lines(input);
+fn print_races(sheet: &RaceSheet, boat: &Boat) {
+    for race in &sheet.races {
+        let wins = boat.wins(race);
+        println!(
+            "Race {} ms, record {} mm: {} ways to win, best hold {} ms for {} mm",
+            race.time, race.distance, wins.count, wins.best_hold, wins.best_distance
+        );
+    }
+}
+
+fn part_two(sheet: &RaceSheet, boat: &Boat) -> i64 {
+    boat.wins(&sheet.races[0]).count
+}
 
-    let time = times_line
-        .split(':')
-        .nth(1)
-        .unwrap()
-        .chars()
-        .filter(|c| !c.is_whitespace())
-        .collect::<String>()
-        .parse::<i64>()
-        .unwrap();
+/// How to read the columns of a race sheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SheetMode {
+    /// Every column is its own race.
+    Separate,
+    /// Bad kerning: all digits on a line form a single number.
+    Kerned,
+}
 
-    let distance = distances_line
-        .split(':')
-        .nth(1)
-        .unwrap()
-        .chars()
-        .filter(|c| !c.is_whitespace())
-        .collect::<String>()
-        .parse::<i64>()
-        .unwrap();
+#[derive(Debug)]
+struct RaceSheet {
+    races: Vec<Race>,
+}
+
+impl RaceSheet {
+    /// Parses a `Time:` line followed by a `Distance:` line. Trailing blank lines are allowed,
+    /// anything else after them is an error.
+    fn parse(input: &str, mode: SheetMode) -> Result<RaceSheet, String> {
+        let mut lines = input.lines().enumerate();
+        let times = parse_sheet_line(lines.next(), "Time", mode)?;
+        let distances = parse_sheet_line(lines.next(), "Distance", mode)?;
 
-    let race = Race { time, distance };
+        if let Some((n, line)) = lines.find(|(_, line)| !line.trim().is_empty()) {
+            return Err(format!("Line {}: unexpected extra line {line:?}", n + 1));
+        }
+        if times.len() != distances.len() {
+            return Err(format!(
+                "{} times but {} distances",
+                times.len(),
+                distances.len()
+            ));
+        }
+        if times.is_empty() {
+            return Err("No races".to_string());
+        }
 
-    calculate_possible_wins(race)
+        let races = times
+            .into_iter()
+            .zip(distances)
+            .map(|(time, distance)| Race { time, distance })
+            .collect();
+        Ok(RaceSheet { races })
+    }
 }
 
-fn get_lines(input: &str) -> (&str, &str) {
-    let mut lines = input.lines();
-    let times_line = lines.next().unwrap();
-    let distances_line = lines.next().unwrap();
+fn parse_sheet_line(
+    line: Option<(usize, &str)>,
+    label: &str,
+    mode: SheetMode,
+) -> Result<Vec<i64>, String> {
+    let (n, line) = line.ok_or(format!("Missing {label} line"))?;
+    let values = line
+        .trim()
+        .strip_prefix(label)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .ok_or(format!(
+            "Line {}: expected '{label}:', found {line:?}",
+            n + 1
+        ))?;
 
-    (times_line, distances_line)
+    let parse = |value: &str| {
+        value
+            .parse::<i64>()
+            .map_err(|_| format!("Line {}: invalid number {value:?}", n + 1))
+    };
+    match mode {
+        SheetMode::Separate => values.split_whitespace().map(parse).collect(),
+        SheetMode::Kerned => {
+            let digits = values.split_whitespace().collect::<String>();
+            if digits.is_empty() {
+                return Ok(Vec::new());
+            }
+            Ok(vec![parse(&digits)?])
+        }
+    }
 }
 
-fn calculate_possible_wins(race: Race) -> i64 {
-    let mut possible_wins = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts and best hold by trying every hold time.
+    fn brute_force(boat: &Boat, race: &Race) -> Wins {
+        let distances = (0..=race.time)
+            .map(|hold| boat.distance(hold, race.time))
+            .collect::<Vec<_>>();
+        let best_distance = *distances.iter().max().unwrap();
+        Wins {
+            count: distances
+                .iter()
+                .filter(|&&distance| distance > race.distance as i128)
+                .count() as i64,
+            best_hold: distances.iter().position(|&d| d == best_distance).unwrap() as i64,
+            best_distance,
+        }
+    }
+
+    /// Small deterministic generator, so that failures can be reproduced.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn below(&mut self, n: i64) -> i64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((self.0 >> 33) % n as u64) as i64
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn wins_match_brute_force() {
+        let mut rng = Lcg(6);
+        for _ in 0..5000 {
+            let mut phases = (0..1 + rng.below(3))
+                .map(|_| Phase {
+                    duration: Some(1 + rng.below(8)),
+                    acceleration: rng.below(4),
+                })
+                .collect::<Vec<_>>();
+            phases.push(Phase {
+                duration: None,
+                acceleration: rng.below(3),
+            });
+            let boat = Boat {
+                phases,
+                max_speed: (rng.below(2) == 0).then(|| rng.below(30)),
+                start_delay: rng.below(5),
+            };
+            let race = Race {
+                time: rng.below(40),
+                distance: rng.below(150),
+            };
 
-    for i in 0..race.time {
-        let distance = (i * VELOCITY) * (race.time - i);
-        if distance > race.distance {
-            possible_wins += 1;
+            assert_eq!(
+                boat.wins(&race),
+                brute_force(&boat, &race),
+                "{boat:?} {race:?}"
+            );
         }
     }
 
-    possible_wins
+    #[test]
+    fn classic_boat_matches_example() {
+        let sheet = "Time:      7  15   30\nDistance:  9  40  200\n";
+        let (races, race) = parse_sheets(sheet).unwrap();
+        assert_eq!(part_one(&races, &Boat::classic()), 288);
+        assert_eq!(part_two(&race, &Boat::classic()), 71503);
+    }
+
+    #[test]
+    fn rejects_negative_boat_settings() {
+        for bad in [
+            &["--phase", "-5:2"][..],
+            &["--phase", "0:2"],
+            &["--phase", "3:-1"],
+            &["--delay", "-1"],
+            &["--max-speed", "-4"],
+        ] {
+            assert!(parse_boat(&args(bad)).is_err(), "{bad:?}");
+        }
+        let boat =
+            parse_boat(&args(&["--phase", "2:3", "--phase", "*:1", "--delay", "0"])).unwrap();
+        assert_eq!(boat.phases.len(), 2);
+    }
 }
@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 
+use common::bench::Bencher;
+
 macro_rules! enum_with_traits {
     ($name:ident, $($variant:ident),+) => {
         #[derive(Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Copy, Clone)]
@@ -157,6 +159,24 @@ fn part_two(input: &str) -> u32 {
 
 fn main() {
     let input = include_str!("input");
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        bench(input);
+        return;
+    }
+
     println!("Part one: {}", part_one(input));
     println!("Part two: {}", part_two(input));
 }
+
+fn bench(input: &str) {
+    let mut bencher = Bencher::new("day7");
+    bencher.bench("parse", || {
+        input
+            .lines()
+            .map(|line| parse_hand(line, false))
+            .collect::<Result<Vec<_>, _>>()
+    });
+    // Each part parses the hands with its own joker rule, so these phases include parsing.
+    bencher.bench("part1", || part_one(input));
+    bencher.bench("part2", || part_two(input));
+}
@@ -1,20 +1,40 @@
 use num::integer::lcm;
 use std::collections::HashMap;
 
+use common::bench::Bencher;
+
 fn main() {
     let input = include_str!("input");
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        bench(input);
+        return;
+    }
+
+    let (instructions, nodes) = parse_input(input);
+
+    println!("Part one: {}", part_one(instructions, &nodes));
+    println!("Part two: {}", part_two(instructions, &nodes));
+}
+
+fn parse_input(input: &str) -> (&str, HashMap<String, (String, String)>) {
     let instructions = input.lines().next().unwrap();
     let nodes = input
         .lines()
         .skip(2)
         .filter_map(parse_line)
         .collect::<HashMap<_, _>>();
+    (instructions, nodes)
+}
 
-    println!("Part one: {}", part_one(instructions, nodes.clone()));
-    println!("Part two: {}", part_two(instructions, nodes.clone()));
+fn bench(input: &str) {
+    let (instructions, nodes) = parse_input(input);
+    let mut bencher = Bencher::new("day8");
+    bencher.bench("parse", || parse_input(input));
+    bencher.bench("part1", || part_one(instructions, &nodes));
+    bencher.bench("part2", || part_two(instructions, &nodes));
 }
 
-fn part_one(instructions: &str, nodes: HashMap<String, (String, String)>) -> u32 {
+fn part_one(instructions: &str, nodes: &HashMap<String, (String, String)>) -> u32 {
     let mut steps = 0;
     let mut value: String = "AAA".to_string();
 
@@ -40,7 +60,7 @@ fn part_one(instructions: &str, nodes: HashMap<String, (String, String)>) -> u32
     steps
 }
 
-fn part_two(instructions: &str, nodes: HashMap<String, (String, String)>) -> usize {
+fn part_two(instructions: &str, nodes: &HashMap<String, (String, String)>) -> usize {
     let start_nodes: Vec<&String> = nodes.keys().filter(|k| k.ends_with('A')).collect();
 
     let steps: Vec<usize> = start_nodes
@@ -1,5 +1,12 @@
+use common::bench::Bencher;
+
 fn main() {
     let input = include_str!("input");
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        bench(input);
+        return;
+    }
+
     let sets = parse_input(input);
     println!("Part one: {}", part_one(&sets));
     println!("Part two: {}", part_two(&sets));
@@ -43,3 +50,11 @@ fn predict_first(vec: &[i32]) -> i32 {
 
     first - predict_first(&diffs)
 }
+
+fn bench(input: &str) {
+    let sets = parse_input(input);
+    let mut bencher = Bencher::new("day9");
+    bencher.bench("parse", || parse_input(input));
+    bencher.bench("part1", || part_one(&sets));
+    bencher.bench("part2", || part_two(&sets));
+}